@@ -3,4 +3,11 @@ pub static NFT_TOKEN_PROGRAM_BYTES: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8b
 pub static COMPUTATION_DECIMALS: u64 = 10u64.pow(12);
 pub static STAKING_SEED: &[u8] = b"staking_instance";
 pub static USER_SEED: &[u8] = b"user_deposit";
-pub static LPTOKEN_SEED: &[u8] = b"lp_token";
\ No newline at end of file
+pub static LPTOKEN_SEED: &[u8] = b"lp_token";
+pub static POOL_SEED: &[u8] = b"staking_pool";
+pub static BPS_DENOMINATOR: u64 = 10_000;
+pub static MAX_EMISSION_EPOCHS: u64 = 240; // 按月划分周期时可覆盖 20 年
+pub static POSITION_SEED: &[u8] = b"position";
+pub static REFERRAL_CONFIG_SEED: &[u8] = b"referral_config";
+pub const RANK_COUNT: usize = 5; // L1-L5
+pub static MAX_TEAM_LEVELS: u64 = 10;
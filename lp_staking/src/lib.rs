@@ -7,142 +7,261 @@ use anchor_lang::solana_program::program::invoke_signed;
 use anchor_spl::token;
 use constants::*;
 use structures::{
-    cancel_staking::*, claim_rewards::*, enter_staking::*, initialize_staking::*,
-    initialize_user::*, Staked, StakingInstance, StakingPool, User,
+    add_pool::*, cancel_staking::*, claim_rewards::*, enter_staking::*, fund_rewards::*,
+    get_reward_runway::*,
+    initialize_referral_config::*, initialize_root_user::*, initialize_staking::*, initialize_user::*, set_pool_schedule::*,
+    set_referral_config::*, set_reward_rate::*, EmissionSchedule, Position, ReferralConfig,
+    StakingInstance, StakingPool, User,
+};
+use tools::{
+    epoch_reward_rate, generate_release_timestamps, scheduled_emission,
+    test_generate_release_timestamp,
 };
-use tools::{generate_release_timestamps, test_generate_release_timestamp};
 
 declare_id!("AAaENevvTe2HM7TcP6AHLq49NhZbB3xKPxYCrjKkyv2Q");
 
-pub fn update_reward_pool(current_timestamp: u64, staking_instance: &mut StakingInstance) {
-    // 遍历每个质押池
-    for pool in staking_instance.pools.iter_mut() {
-        // 如果没有份额，跳过此池
-        if pool.total_shares == 0 {
-            continue;
-        }
-        // 计算时间差（当前时间戳 - 上次奖励时间戳）
-        let time_diff = current_timestamp
-            .checked_sub(pool.last_reward_timestamp)
-            .unwrap_or(0);
+pub fn update_reward_pool(
+    current_timestamp: u64,
+    staking_instance: &mut StakingInstance,
+    pool: &mut StakingPool,
+) {
+    // 到达预定的生效时间后，先按旧速率结算到生效时间，再切换为新速率
+    if pool.pending_rate_effective_at != 0 && pool.pending_rate_effective_at <= current_timestamp {
+        accrue_reward(pool.pending_rate_effective_at, staking_instance, pool);
+        pool.reward_token_per_sec = pool.pending_reward_rate;
+        pool.pending_reward_rate = 0;
+        pool.pending_rate_effective_at = 0;
+    }
+    accrue_reward(current_timestamp, staking_instance, pool);
+}
 
-        // 如果时间差为 0，跳过此池
-        if time_diff == 0 {
-            continue;
-        }
+fn accrue_reward(
+    current_timestamp: u64,
+    staking_instance: &mut StakingInstance,
+    pool: &mut StakingPool,
+) {
+    // 超过 rewards_end_at 后不再产生奖励
+    let current_timestamp = current_timestamp.min(pool.rewards_end_at);
 
-        // 计算池子的总奖励收入（奖励速率 * 时间差）
-        let income = pool
-            .reward_token_per_sec
-            .checked_mul(time_diff)
-            .unwrap_or(0);
+    // 如果没有份额，不产生奖励，但结算时间照常推进，之后的速率不会作用到无人质押的时间段
+    if pool.total_shares == 0 {
+        pool.last_reward_timestamp = pool.last_reward_timestamp.max(current_timestamp);
+        return;
+    }
 
-        // 更新 `accumulated_reward_per_share`
-        if pool.total_shares > 0 {
-            // 每份奖励计算
-            let reward_per_share = (income as u128)
-                .checked_mul(COMPUTATION_DECIMALS as u128) // 精度调整
-                .unwrap_or(0)
-                .checked_div(pool.total_shares as u128) // 每份奖励
-                .unwrap_or(0) as u64;
+    // 计算时间差（当前时间戳 - 上次奖励时间戳）
+    let time_diff = current_timestamp
+        .checked_sub(pool.last_reward_timestamp)
+        .unwrap_or(0);
 
-            // 累加每份奖励的累计值
-            pool.accumulated_reward_per_share = pool
-                .accumulated_reward_per_share
-                .checked_add(reward_per_share)
-                .unwrap_or(pool.accumulated_reward_per_share); // 防止溢出
-        }
+    // 如果时间差为 0，直接返回
+    if time_diff == 0 {
+        return;
+    }
 
-        // 更新最后奖励时间戳为当前时间戳
-        pool.last_reward_timestamp = current_timestamp;
+    // 按排放计划计算池子的总奖励收入，跨越周期时按各周期的速率分段累加
+    let scheduled = scheduled_emission(
+        &pool.emission,
+        pool.reward_token_per_sec,
+        pool.last_reward_timestamp,
+        current_timestamp,
+    );
+    // 累计排放不能超过该池的排放上限，也不能超过协议已注入但尚未排放的奖励；
+    // 注入不足期间按可排放的数量降低实际排放，不会事后补发
+    let remaining = pool.emission_cap.saturating_sub(pool.total_emitted);
+    // 推荐奖励额外发放时，每排放 income 同时预留 income * referral_reserve_bps / 10000，排放与预留之和不超过可用的注入
+    let fundable = (unemitted_funding(staking_instance) as u128)
+        .checked_mul(BPS_DENOMINATOR as u128)
+        .unwrap_or(0)
+        / (BPS_DENOMINATOR.saturating_add(staking_instance.referral_reserve_bps) as u128);
+    let income = scheduled
+        .min(remaining as u128)
+        .min(fundable) as u64;
+
+    // 更新 `accumulated_reward_per_share`
+    if pool.total_shares > 0 {
+        // 每份奖励计算
+        let reward_per_share = (income as u128)
+            .checked_mul(COMPUTATION_DECIMALS as u128) // 精度调整
+            .unwrap_or(0)
+            .checked_div(pool.total_shares as u128) // 每份奖励
+            .unwrap_or(0) as u64;
+
+        // 累加每份奖励的累计值
+        pool.accumulated_reward_per_share = pool
+            .accumulated_reward_per_share
+            .checked_add(reward_per_share)
+            .unwrap_or(pool.accumulated_reward_per_share); // 防止溢出
+        pool.total_emitted = pool.total_emitted.saturating_add(income);
+        staking_instance.total_emitted = staking_instance.total_emitted.saturating_add(income);
+        staking_instance.referral_reserve = staking_instance
+            .referral_reserve
+            .saturating_add(referral_reserve_for(staking_instance, income));
     }
+
+    // 更新最后奖励时间戳为当前时间戳
+    pool.last_reward_timestamp = current_timestamp;
 }
 
-pub fn store_pending_reward(
-    staking_instance: &mut StakingInstance,
-    user_instance: &mut User,
-    staked_info_number: u64, // 修改为索引
-) -> Result<()> {
-    // 获取用户对应的质押信息
-    let staked_info = &mut user_instance.staked_info[staked_info_number as usize];
+/// 已注入但尚未排放、也没有为推荐奖励预留的奖励
+pub fn unemitted_funding(staking_instance: &StakingInstance) -> u64 {
+    staking_instance
+        .total_funded
+        .saturating_sub(staking_instance.total_emitted)
+        .saturating_sub(staking_instance.referral_reserve)
+}
 
-    // 确保该质押池已被质押
-    if !staked_info.is_staked {
-        return Ok(()); // 如果该质押池没有质押，直接返回
+/// 排放 amount 时需要为额外发放的推荐奖励预留的数量
+pub fn referral_reserve_for(staking_instance: &StakingInstance, amount: u64) -> u64 {
+    ((amount as u128) * (staking_instance.referral_reserve_bps as u128) / (BPS_DENOMINATOR as u128))
+        .min(u64::MAX as u128) as u64
+}
+
+/// 推荐奖励额外发放时上级最多获得的万分比（直推奖励 + 最高等级奖励），从用户奖励中扣除时为 0
+pub fn referral_reserve_bps(config: &ReferralConfig) -> u64 {
+    if config.deduct_from_user {
+        return 0;
     }
+    let max_rank_bps = config.rank_reward_bps.iter().copied().max().unwrap_or(0);
+    config.direct_reward_bps.saturating_add(max_rank_bps)
+}
 
-    // 获取质押类型对应的池子
-    let stake_type = staked_info.stake_type as usize;
+/// 按时间顺序记录质押池从上次结算开始的排放速率变化（时间, 每秒排放的变化量）：只遍历一次排放周期，
+/// 速率在周期边界按衰减比例下降，在预定速率生效时切换，到 rewards_end_at 为止；
+/// 到达剩余排放上限的那一段只排放到上限，最后不足一秒速率的余数在下一秒排放。没有份额的质押池不排放
+fn push_emission_changes(pool: &StakingPool, changes: &mut Vec<(u64, i128)>) {
+    if pool.total_shares == 0 {
+        return;
+    }
+    let schedule = &pool.emission;
+    let keep_bps = BPS_DENOMINATOR.saturating_sub(schedule.decay_bps) as u128;
+    let switch_at = pool.pending_rate_effective_at;
+    let mut remaining = pool.emission_cap.saturating_sub(pool.total_emitted) as u128;
+    let mut cursor = pool.last_reward_timestamp;
+    let mut epoch = if schedule.epoch_duration == 0 {
+        0
+    } else {
+        cursor.saturating_sub(schedule.emission_start) / schedule.epoch_duration
+    };
+    let base_rate = if switch_at != 0 && switch_at <= cursor {
+        pool.pending_reward_rate
+    } else {
+        pool.reward_token_per_sec
+    };
+    let mut rate = epoch_reward_rate(schedule, base_rate, epoch) as u128;
+
+    while cursor < pool.rewards_end_at && remaining > 0 {
+        if schedule.epoch_duration > 0 && epoch >= schedule.epoch_count {
+            break;
+        }
+        // 速率保持不变直到周期结束、预定速率生效或奖励结束
+        let epoch_end = if schedule.epoch_duration == 0 {
+            u64::MAX
+        } else {
+            schedule
+                .emission_start
+                .saturating_add(schedule.epoch_duration.saturating_mul(epoch + 1))
+        };
+        let mut next = pool.rewards_end_at.min(epoch_end);
+        if switch_at > cursor && switch_at < next {
+            next = switch_at;
+        }
 
-    // 检查 stake_type 是否为有效池子索引
-    // if stake_type >= staking_instance.pools.len() {
-    //     return Err(ErrorCode::InvalidStakeType.into()); // 自定义错误类型
-    // }
+        if rate > 0 {
+            let segment = rate * (next - cursor) as u128;
+            if segment >= remaining {
+                let full_seconds = (remaining / rate) as u64;
+                let rest = remaining % rate;
+                changes.push((cursor, rate as i128));
+                changes.push((cursor + full_seconds, -(rate as i128)));
+                if rest > 0 {
+                    changes.push((cursor + full_seconds, rest as i128));
+                    changes.push((cursor + full_seconds + 1, -(rest as i128)));
+                }
+                return;
+            }
+            changes.push((cursor, rate as i128));
+            changes.push((next, -(rate as i128)));
+            remaining -= segment;
+        }
 
-    // 获取对应池子
-    let pool = &staking_instance.pools[stake_type];
+        cursor = next;
+        if cursor == epoch_end {
+            epoch += 1;
+            rate = rate * keep_bps / BPS_DENOMINATOR as u128;
+        }
+        if cursor == switch_at {
+            rate = epoch_reward_rate(schedule, pool.pending_reward_rate, epoch) as u128;
+        }
+    }
+}
+
+/// 估算已注入的奖励还能排放的秒数：按各质押池的排放计划、预定速率、rewards_end_at 和 emission_cap 推算未来的排放，
+/// 每份排放同时计入为推荐奖励预留的部分，已经预留的数量不再可用。所有质押池停止排放前注入都够用时返回 u64::MAX
+pub fn reward_runway(
+    staking_instance: &StakingInstance,
+    pools: &[StakingPool],
+    current_timestamp: u64,
+) -> u64 {
+    let available = unemitted_funding(staking_instance) as u128;
+    let factor = BPS_DENOMINATOR as u128 + staking_instance.referral_reserve_bps as u128;
+    // 排放 emission 需要 emission * factor / 10000 的注入，不超过 available 时 emission 最多为 max_emission
+    let max_emission = ((available + 1) * BPS_DENOMINATOR as u128 - 1) / factor;
+
+    let mut changes = Vec::new();
+    for pool in pools {
+        push_emission_changes(pool, &mut changes);
+    }
+    changes.sort_unstable_by_key(|(time, _)| *time);
+
+    // 合并后的总速率在相邻两个变化之间保持不变，注入在某一段内耗尽时直接求出耗尽的时间
+    let mut emitted: u128 = 0;
+    let mut rate: i128 = 0;
+    let mut cursor = 0;
+    for (time, delta) in changes {
+        if time > cursor && rate > 0 {
+            let segment = rate as u128 * (time - cursor) as u128;
+            if emitted + segment > max_emission {
+                let exhausted_at = cursor + ((max_emission - emitted) / rate as u128) as u64;
+                return exhausted_at.saturating_sub(current_timestamp);
+            }
+            emitted += segment;
+        }
+        cursor = time;
+        rate += delta;
+    }
+    u64::MAX
+}
 
-    // 计算用户在该池子的待领取奖励
-    let pending_reward = (staked_info.deposited_amount as u128)
+pub fn store_pending_reward(pool: &StakingPool, position: &mut Position) -> Result<()> {
+    // 计算用户在该仓位的待领取奖励
+    let pending_reward = (position.deposited_amount as u128)
         .checked_mul(pool.accumulated_reward_per_share as u128)
         .and_then(|v| v.checked_div(COMPUTATION_DECIMALS as u128))
-        .and_then(|v| v.checked_sub(staked_info.reward_debt as u128))
+        .and_then(|v| v.checked_sub(position.reward_debt as u128))
         .unwrap_or(0) as u64; // 最终将结果转换回 u64 类型，如果需要
                               // 如果待领取奖励为 0，直接返回
     if pending_reward == 0 {
         return Ok(());
     }
 
-    // 更新该质押池的累计奖励
-    staked_info.accumulated_reward = staked_info
+    // 更新该仓位的累计奖励
+    position.accumulated_reward = position
         .accumulated_reward
         .checked_add(pending_reward)
-        .unwrap_or(staked_info.accumulated_reward); // 防止溢出
+        .unwrap_or(position.accumulated_reward); // 防止溢出
 
-    // 更新用户的 reward_debt 为最新的池子状态
-    staked_info.reward_debt = (staked_info.deposited_amount as u128)
+    // 更新仓位的 reward_debt 为最新的池子状态
+    position.reward_debt = (position.deposited_amount as u128)
         .checked_mul(pool.accumulated_reward_per_share as u128)
         .and_then(|v| v.checked_div(COMPUTATION_DECIMALS as u128))
-        .unwrap_or(staked_info.reward_debt as u128) as u64;
+        .unwrap_or(position.reward_debt as u128) as u64;
     Ok(())
 }
 
-pub fn update_reward_debt(
-    staking_instance: &mut StakingInstance,
-    user_instance: &mut User,
-    staked_info_number: u64, // 用户质押池的索引
-) {
-    // 获取用户对应的质押信息
-    let staked_info = &mut user_instance.staked_info[staked_info_number as usize];
-
-    // 确保该质押池已被质押
-    if !staked_info.is_staked {
-        return; // 如果该质押池没有质押，直接返回
-    }
-    // 获取质押类型对应的池子
-    let stake_type = staked_info.stake_type as usize;
-    // 检查 stake_type 是否为有效池子索引
-    if stake_type >= staking_instance.pools.len() {
-        return; // 无效的池子索引，直接返回
-    }
-
-    // 获取对应池子
-    let pool = &staking_instance.pools[stake_type];
-
-    // msg!(
-    //     "Hello world!!",
-    //     pool.accumulated_reward_per_share,
-    //     staked_info.deposited_amount
-    // );
-    // 更新该质押池的 reward_debt
-    // msg!(
-    //     "staked_info
-    //     .deposited_amount",
-    //     staked_info.deposited_amount
-    // );
-    // msg!("accumulated_reward_per_share", accumulated_reward_per_share);
-
-    staked_info.reward_debt = (staked_info.deposited_amount as u128)
+pub fn update_reward_debt(pool: &StakingPool, position: &mut Position) {
+    // 更新该仓位的 reward_debt
+    position.reward_debt = (position.deposited_amount as u128)
         .checked_mul(pool.accumulated_reward_per_share as u128)
         .and_then(|v| v.checked_div(COMPUTATION_DECIMALS as u128))
         .unwrap_or(0) as u64;
@@ -151,8 +270,164 @@ pub fn update_reward_debt(
 pub fn is_authorized(user: &Pubkey, authority: &Pubkey) -> bool {
     user == authority
 }
-pub fn can_unstake(staked: &Staked, current_timestamp: u64) -> bool {
-    staked.is_staked && staked.stake_end_time <= current_timestamp
+pub fn can_unstake(position: &Position, current_timestamp: u64) -> bool {
+    position.stake_end_time <= current_timestamp
+}
+
+/// 根据用户账户和 nonce 计算质押仓位的 PDA 地址
+pub fn position_address(user_instance: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            POSITION_SEED.as_ref(),
+            user_instance.as_ref(),
+            nonce.to_le_bytes().as_ref(),
+        ],
+        &ID,
+    )
+    .0
+}
+
+/// 客户端辅助函数：列出用户尚未关闭的质押仓位
+/// fetch 按地址返回账户原始数据（如 RpcClient::get_account_data），账户不存在时返回 None
+pub fn list_open_positions<F>(user_instance: &Pubkey, user: &User, mut fetch: F) -> Vec<(Pubkey, Position)>
+where
+    F: FnMut(&Pubkey) -> Option<Vec<u8>>,
+{
+    (0..user.position_count)
+        .filter_map(|nonce| {
+            let address = position_address(user_instance, nonce);
+            let data = fetch(&address)?;
+            let position = Position::try_deserialize(&mut data.as_slice()).ok()?;
+            Some((address, position))
+        })
+        .collect()
+}
+
+pub fn validate_referral_config(
+    team_levels: u64,
+    rank_thresholds: &[u64; RANK_COUNT],
+    rank_reward_bps: &[u64; RANK_COUNT],
+    evaluation_period: u64,
+    direct_reward_bps: u64,
+) -> bool {
+    // 门槛必须大于 0 且逐级递增
+    let thresholds_increasing = rank_thresholds[0] > 0
+        && rank_thresholds.windows(2).all(|pair| pair[0] < pair[1]);
+    team_levels > 0
+        && team_levels <= MAX_TEAM_LEVELS
+        && thresholds_increasing
+        && rank_reward_bps.iter().all(|bps| *bps <= BPS_DENOMINATOR)
+        && evaluation_period > 0
+        && direct_reward_bps <= BPS_DENOMINATOR
+}
+
+/// 根据本评估周期新增的团队业绩计算能达到的最高等级，0 表示未达到 L1；
+/// 等级只看新增业绩，团队当前的质押量 team_volume 不参与评级，业绩不再增长时等级会逐期下降
+pub fn rank_for_volume(config: &ReferralConfig, period_volume: u64) -> u8 {
+    config
+        .rank_thresholds
+        .iter()
+        .take_while(|threshold| period_volume >= **threshold)
+        .count() as u8
+}
+
+/// 结束已经到期的评估周期：本周期新增业绩达到当前等级的门槛才能保级，否则降一级（不低于本周期业绩对应的等级）；
+/// 之后每个完整的周期都没有新增业绩，再各降一级。周期按 evaluation_period 对齐，不随评估时间漂移
+pub fn evaluate_rank(config: &ReferralConfig, user: &mut User, current_timestamp: u64) {
+    let elapsed = current_timestamp.saturating_sub(user.last_rank_evaluation) / config.evaluation_period;
+    if elapsed == 0 {
+        return;
+    }
+    let period_rank = rank_for_volume(config, user.period_volume);
+    if period_rank < user.rank {
+        user.rank = period_rank.max(user.rank - 1);
+    }
+    let missed = (elapsed - 1).min(RANK_COUNT as u64) as u8;
+    user.rank = user.rank.saturating_sub(missed);
+    user.period_volume = 0;
+    user.last_rank_evaluation = user
+        .last_rank_evaluation
+        .saturating_add(elapsed.saturating_mul(config.evaluation_period));
+}
+
+/// 下级质押时累加团队业绩，本周期新增业绩达到更高等级的门槛时立即升级
+pub fn add_team_volume(
+    config: &ReferralConfig,
+    user: &mut User,
+    amount: u64,
+    current_timestamp: u64,
+) -> Result<()> {
+    // 先按原业绩结束已经到期的评估周期
+    evaluate_rank(config, user, current_timestamp);
+    user.team_volume = user
+        .team_volume
+        .checked_add(amount)
+        .ok_or(ErrorCode::Overflow)?;
+    user.period_volume = user
+        .period_volume
+        .checked_add(amount)
+        .ok_or(ErrorCode::Overflow)?;
+    user.rank = user.rank.max(rank_for_volume(config, user.period_volume));
+    Ok(())
+}
+
+/// 下级解除质押时扣减团队业绩，本周期新增业绩同样扣减，最多扣到 0
+pub fn remove_team_volume(
+    config: &ReferralConfig,
+    user: &mut User,
+    amount: u64,
+    current_timestamp: u64,
+) {
+    evaluate_rank(config, user, current_timestamp);
+    user.team_volume = user.team_volume.saturating_sub(amount);
+    user.period_volume = user.period_volume.saturating_sub(amount);
+}
+
+/// 等级对应的奖励万分比，没有等级时为 0
+pub fn rank_reward_bps(config: &ReferralConfig, rank: u8) -> u64 {
+    if rank == 0 {
+        return 0;
+    }
+    config.rank_reward_bps[(rank as usize - 1).min(RANK_COUNT - 1)]
+}
+
+/// 按等级计算上级从下级领取的奖励中获得的奖励
+pub fn calculate_rank_reward(config: &ReferralConfig, rank: u8, amount: u64) -> u64 {
+    let bps = rank_reward_bps(config, rank);
+    ((amount as u128) * (bps as u128) / (BPS_DENOMINATOR as u128)) as u64
+}
+
+/// 按级差计算更上层推荐人的等级奖励：等级比例高于下层已经发放的最高比例 paid_bps 时获得差额部分，并更新 paid_bps；
+/// 整条推荐链发放的等级奖励合计不超过最高等级的比例
+pub fn differential_rank_reward(
+    config: &ReferralConfig,
+    rank: u8,
+    paid_bps: &mut u64,
+    amount: u64,
+) -> u64 {
+    let bps = rank_reward_bps(config, rank);
+    if bps <= *paid_bps {
+        return 0;
+    }
+    let reward = (amount as u128) * ((bps - *paid_bps) as u128) / (BPS_DENOMINATOR as u128);
+    *paid_bps = bps;
+    reward as u64
+}
+
+/// 拆分一次领取的奖励，返回（用户实际获得，上级获得）
+/// 上级奖励 = 等级奖励 + 总质押超过门槛时的直推奖励；扣除模式下从用户奖励中扣除，最多扣完
+pub fn split_referral_reward(config: &ReferralConfig, superior: &User, amount: u64) -> (u64, u64) {
+    let mut superior_reward = calculate_rank_reward(config, superior.rank, amount) as u128;
+    if superior.total_deposited_amount > config.qualification_threshold {
+        superior_reward += (amount as u128) * (config.direct_reward_bps as u128)
+            / (BPS_DENOMINATOR as u128);
+    }
+    if config.deduct_from_user {
+        let superior_reward = superior_reward.min(amount as u128) as u64;
+        (amount - superior_reward, superior_reward)
+    } else {
+        (amount, superior_reward.min(u64::MAX as u128) as u64)
+    }
 }
 
 pub fn calculate_referral_reward(user: &User, amount: u64) -> u64 {
@@ -164,13 +439,7 @@ pub fn calculate_referral_reward(user: &User, amount: u64) -> u64 {
 #[program]
 pub mod gdtc_staking {
     use super::*;
-    pub fn initialize_staking(
-        ctx: Context<InitializeStaking>,
-        reward_per_sec_3_months: u64,
-        reward_per_sec_6_months: u64,
-        reward_per_sec_12_months: u64,
-        start_reward_timestamp: u64,
-    ) -> Result<()> {
+    pub fn initialize_staking(ctx: Context<InitializeStaking>) -> Result<()> {
         let staking_instance = &mut ctx.accounts.staking_instance;
 
         // 设置基础字段
@@ -178,6 +447,14 @@ pub mod gdtc_staking {
         staking_instance.reward_token_mint = ctx.accounts.reward_token_mint.key();
         staking_instance.staking_token_mint = ctx.accounts.staking_token_mint.key();
         staking_instance.lp_token_account = ctx.accounts.lp_token_account.key();
+        staking_instance.pool_count = 0;
+        staking_instance.total_emission_cap = 0;
+        staking_instance.root_user = Pubkey::default();
+        staking_instance.outstanding_rewards = 0;
+        staking_instance.total_funded = 0;
+        staking_instance.total_emitted = 0;
+        staking_instance.referral_reserve_bps = 0;
+        staking_instance.referral_reserve = 0;
 
         let program_id = ctx.program_id; // 获取当前合约的程序ID
                                          // 计算 staking_instance 的派生地址
@@ -188,71 +465,445 @@ pub mod gdtc_staking {
             return Err(ErrorCode::PdaAccountIsNotMatch.into());
         }
 
-        // 初始化 3 个质押池
-        staking_instance.pools = [
-            StakingPool {
-                stake_type: 0, // 3 个月
-                reward_token_per_sec: reward_per_sec_3_months,
-                accumulated_reward_per_share: 0,
-                last_reward_timestamp: start_reward_timestamp,
-                total_shares: 0,
-            },
-            StakingPool {
-                stake_type: 1, // 6 个月
-                reward_token_per_sec: reward_per_sec_6_months,
-                accumulated_reward_per_share: 0,
-                last_reward_timestamp: start_reward_timestamp,
-                total_shares: 0,
-            },
-            StakingPool {
-                stake_type: 2, // 12 个月
-                reward_token_per_sec: reward_per_sec_12_months,
-                accumulated_reward_per_share: 0,
-                last_reward_timestamp: start_reward_timestamp,
-                total_shares: 0,
-            },
-        ];
+        // 质押池通过 add_pool 单独创建
         Ok(())
     }
 
+    // 管理员新增质押池，例如 3/6/12 个月的常规池或 1/24 个月的活动池
+    pub fn add_pool(
+        ctx: Context<AddPool>,
+        duration_months: u64, // 锁仓月数
+        reward_per_sec: u64,  // 每秒奖励代币数量
+        start: u64,           // 开始接受质押和计算奖励的时间戳
+        end: u64,             // 停止接受质押的时间戳
+        rewards_end_at: u64,  // 停止产生奖励的时间戳，不能早于 end
+        epoch_duration: u64,  // 排放周期秒数，为 0 表示速率不衰减
+        epoch_count: u64,     // 排放周期数量
+        decay_bps: u64,       // 每个周期速率下降的万分比
+        emission_cap: u64,    // 该池最多排放的奖励总量
+    ) -> Result<()> {
+        let staking_instance = &mut ctx.accounts.staking_instance;
+        let pool = &mut ctx.accounts.pool;
+
+        let program_id = ctx.program_id;
+        let (expected_staking_address, _bump_seed) =
+            Pubkey::find_program_address(&[crate::STAKING_SEED.as_ref()], program_id);
+
+        // 确保 staking_instance 是由合约程序派生的
+        if staking_instance.key() != expected_staking_address {
+            return Err(ErrorCode::InvalidStakingInstance.into());
+        }
+        if !is_authorized(&ctx.accounts.authority.key(), &staking_instance.authority) {
+            return Err(ErrorCode::NotAuthority.into());
+        }
+        if duration_months == 0 || end <= start || rewards_end_at < end {
+            return Err(ErrorCode::InvalidPoolConfig.into());
+        }
+        if decay_bps > BPS_DENOMINATOR
+            || (epoch_duration > 0 && (epoch_count == 0 || epoch_count > MAX_EMISSION_EPOCHS))
+        {
+            return Err(ErrorCode::InvalidPoolConfig.into());
+        }
+        // 所有质押池的排放上限之和不能超过通过 fund_rewards 注入的奖励
+        let total_emission_cap = staking_instance
+            .total_emission_cap
+            .checked_add(emission_cap)
+            .ok_or(ErrorCode::Overflow)?;
+        if total_emission_cap > staking_instance.total_funded {
+            return Err(ErrorCode::EmissionCapExceedsFunding.into());
+        }
+
+        pool.staking_instance = staking_instance.key();
+        pool.pool_id = staking_instance.pool_count;
+        pool.duration_months = duration_months;
+        pool.staking_open_from = start;
+        pool.staking_closes_at = end;
+        pool.rewards_end_at = rewards_end_at;
+        pool.reward_token_per_sec = reward_per_sec;
+        pool.accumulated_reward_per_share = 0;
+        pool.last_reward_timestamp = start;
+        pool.total_shares = 0;
+        pool.emission = EmissionSchedule {
+            emission_start: start,
+            epoch_duration,
+            epoch_count,
+            decay_bps,
+        };
+        pool.emission_cap = emission_cap;
+        pool.total_emitted = 0;
+        pool.pending_reward_rate = 0;
+        pool.pending_rate_effective_at = 0;
+
+        staking_instance.total_emission_cap = total_emission_cap;
+        staking_instance.pool_count = staking_instance
+            .pool_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        emit!(PoolCreated {
+            pool: pool.key(),
+            pool_id: pool.pool_id,
+            duration_months,
+            reward_per_sec,
+            staking_open_from: start,
+            staking_closes_at: end,
+            rewards_end_at,
+            emission_cap,
+        });
+
+        Ok(())
+    }
+
+    // 注入奖励代币并记录到 total_funded，任何账户都可以注入，例如代币合约分配 LP 挖矿份额时
+    pub fn fund_rewards(ctx: Context<FundRewards>, amount: u64) -> Result<()> {
+        let program_id = ctx.program_id;
+        let (expected_staking_address, _bump_seed) =
+            Pubkey::find_program_address(&[crate::STAKING_SEED.as_ref()], program_id);
+        if ctx.accounts.staking_instance.key() != expected_staking_address {
+            return Err(ErrorCode::InvalidStakingInstance.into());
+        }
+        let (expected_pda_address, _bump_seed) =
+            Pubkey::find_program_address(&[crate::LPTOKEN_SEED.as_ref()], program_id);
+        if expected_pda_address != ctx.accounts.gdtc_reward_out_account.owner.key() {
+            return Err(ErrorCode::PdaAccountIsNotMatch.into());
+        }
+        if ctx.accounts.staking_instance.reward_token_mint != ctx.accounts.gdtc_reward_out_account.mint {
+            return Err(ErrorCode::MintAccountIsNotMatch.into());
+        }
+        if ctx.accounts.funder_token_account.amount < amount {
+            return Err(ErrorCode::TokenAccountBalanceInsufficient.into());
+        }
+
+        // 转移奖励代币到合约的奖励账户
+        token::transfer(ctx.accounts.into_transfer_to_reward_context(), amount)?;
+
+        let staking_instance = &mut ctx.accounts.staking_instance;
+        staking_instance.total_funded = staking_instance
+            .total_funded
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        emit!(RewardsFunded {
+            funder: ctx.accounts.funder.key(),
+            amount,
+            total_funded: staking_instance.total_funded,
+        });
+
+        Ok(())
+    }
+
+    // 查询已注入的奖励还能排放的秒数，remaining_accounts 按 pool_id 顺序传入该质押实例的所有质押池
+    #[view]
+    pub fn get_reward_runway<'info>(
+        ctx: Context<'_, '_, '_, 'info, GetRewardRunway<'info>>,
+    ) -> Result<u64> {
+        let staking_instance = &ctx.accounts.staking_instance;
+        if ctx.remaining_accounts.len() as u64 != staking_instance.pool_count {
+            return Err(ErrorCode::InvalidPoolAccount.into());
+        }
+        let mut pools = Vec::with_capacity(ctx.remaining_accounts.len());
+        for (pool_id, pool_info) in ctx.remaining_accounts.iter().enumerate() {
+            // 质押池必须是本程序按 [POOL_SEED, staking_instance, pool_id] 派生的账户
+            let (expected_pool_address, _bump_seed) = Pubkey::find_program_address(
+                &[
+                    crate::POOL_SEED.as_ref(),
+                    staking_instance.key().as_ref(),
+                    (pool_id as u64).to_le_bytes().as_ref(),
+                ],
+                ctx.program_id,
+            );
+            if pool_info.owner != ctx.program_id || pool_info.key() != expected_pool_address {
+                return Err(ErrorCode::InvalidPoolAccount.into());
+            }
+            let pool: Account<StakingPool> = Account::try_from(pool_info)?;
+            if pool.staking_instance != staking_instance.key() {
+                return Err(ErrorCode::InvalidPoolAccount.into());
+            }
+            pools.push(pool.into_inner());
+        }
+
+        let clock = Clock::get().map_err(|_| ErrorCode::ClockUnavailable)?;
+        Ok(reward_runway(staking_instance, &pools, clock.unix_timestamp as u64))
+    }
+
+    // 管理员调整质押池的开放、关闭和奖励结束时间
+    pub fn set_pool_schedule(
+        ctx: Context<SetPoolSchedule>,
+        staking_open_from: u64, // 开始接受质押的时间戳
+        staking_closes_at: u64, // 停止接受质押的时间戳
+        rewards_end_at: u64,    // 停止产生奖励的时间戳
+    ) -> Result<()> {
+        let staking_instance = &mut ctx.accounts.staking_instance;
+        let pool = &mut ctx.accounts.pool;
+
+        if !is_authorized(&ctx.accounts.authority.key(), &staking_instance.authority) {
+            return Err(ErrorCode::NotAuthority.into());
+        }
+        if pool.staking_instance != staking_instance.key() {
+            return Err(ErrorCode::InvalidPoolAccount.into());
+        }
+        if staking_open_from >= staking_closes_at || staking_closes_at > rewards_end_at {
+            return Err(ErrorCode::InvalidPoolConfig.into());
+        }
+
+        let clock = Clock::get().map_err(|_| ErrorCode::ClockUnavailable)?;
+        let current_timestamp = clock.unix_timestamp as u64;
+
+        // 先按原计划结算到当前时间，已经产生的奖励不受影响
+        update_reward_pool(current_timestamp, staking_instance, pool);
+
+        // 奖励结束时间不能早于已结算的时间
+        if rewards_end_at < current_timestamp.min(pool.rewards_end_at) {
+            return Err(ErrorCode::InvalidPoolConfig.into());
+        }
+
+        // 奖励已经结束或池中无人质押时，从当前时间开始重新计算，不补发中断期间的奖励
+        if pool.last_reward_timestamp < current_timestamp {
+            pool.last_reward_timestamp = current_timestamp;
+        }
+
+        pool.staking_open_from = staking_open_from;
+        pool.staking_closes_at = staking_closes_at;
+        pool.rewards_end_at = rewards_end_at;
+
+        emit!(PoolScheduleUpdated {
+            pool: pool.key(),
+            staking_open_from,
+            staking_closes_at,
+            rewards_end_at,
+        });
+
+        Ok(())
+    }
+
+    // 管理员调整质押池的奖励速率，effective_at 为 0 或不晚于当前时间时立即生效，否则在该时间生效
+    pub fn set_reward_rate(
+        ctx: Context<SetRewardRate>,
+        new_rate: u64,     // 新的每秒奖励代币数量（第 0 个排放周期的速率）
+        effective_at: u64, // 生效时间戳
+    ) -> Result<()> {
+        let staking_instance = &mut ctx.accounts.staking_instance;
+        let pool = &mut ctx.accounts.pool;
+
+        if !is_authorized(&ctx.accounts.authority.key(), &staking_instance.authority) {
+            return Err(ErrorCode::NotAuthority.into());
+        }
+        if pool.staking_instance != staking_instance.key() {
+            return Err(ErrorCode::InvalidPoolAccount.into());
+        }
+
+        let clock = Clock::get().map_err(|_| ErrorCode::ClockUnavailable)?;
+        let current_timestamp = clock.unix_timestamp as u64;
+
+        // 先按旧速率结算到当前时间，已经产生的奖励不受影响
+        update_reward_pool(current_timestamp, staking_instance, pool);
+
+        let old_rate = pool.reward_token_per_sec;
+        let effective_at = effective_at.max(current_timestamp);
+        if effective_at == current_timestamp {
+            pool.reward_token_per_sec = new_rate;
+            pool.pending_reward_rate = 0;
+            pool.pending_rate_effective_at = 0;
+        } else {
+            // 覆盖之前尚未生效的预定速率
+            pool.pending_reward_rate = new_rate;
+            pool.pending_rate_effective_at = effective_at;
+        }
+
+        emit!(RewardRateUpdated {
+            pool: pool.key(),
+            old_rate,
+            new_rate,
+            effective_at,
+        });
+
+        Ok(())
+    }
+
+    // 管理员创建推荐等级配置
+    pub fn initialize_referral_config(
+        ctx: Context<InitializeReferralConfig>,
+        team_levels: u64,                       // 统计团队业绩的下级层数
+        rank_thresholds: [u64; RANK_COUNT],     // 升级到 L1-L5 需要的团队业绩
+        rank_reward_bps: [u64; RANK_COUNT],     // L1-L5 的奖励万分比
+        evaluation_period: u64,                 // 重新评估等级的周期（秒）
+        direct_reward_bps: u64,                 // 直接上级的推荐奖励万分比
+        qualification_threshold: u64,           // 上级获得直推奖励需要的总质押
+        deduct_from_user: bool,                 // 推荐奖励是否从用户奖励中扣除
+    ) -> Result<()> {
+        let staking_instance = &mut ctx.accounts.staking_instance;
+        let referral_config = &mut ctx.accounts.referral_config;
+
+        if !is_authorized(&ctx.accounts.authority.key(), &staking_instance.authority) {
+            return Err(ErrorCode::NotAuthority.into());
+        }
+        if !validate_referral_config(
+            team_levels,
+            &rank_thresholds,
+            &rank_reward_bps,
+            evaluation_period,
+            direct_reward_bps,
+        ) {
+            return Err(ErrorCode::InvalidReferralConfig.into());
+        }
+
+        referral_config.staking_instance = staking_instance.key();
+        referral_config.team_levels = team_levels;
+        referral_config.rank_thresholds = rank_thresholds;
+        referral_config.rank_reward_bps = rank_reward_bps;
+        referral_config.evaluation_period = evaluation_period;
+        referral_config.direct_reward_bps = direct_reward_bps;
+        referral_config.qualification_threshold = qualification_threshold;
+        referral_config.deduct_from_user = deduct_from_user;
+        staking_instance.referral_reserve_bps = referral_reserve_bps(referral_config);
+
+        Ok(())
+    }
+
+    // 管理员调整推荐等级配置，已有等级在下次评估时按新配置计算
+    pub fn set_referral_config(
+        ctx: Context<SetReferralConfig>,
+        team_levels: u64,
+        rank_thresholds: [u64; RANK_COUNT],
+        rank_reward_bps: [u64; RANK_COUNT],
+        evaluation_period: u64,
+        direct_reward_bps: u64,                 // 直接上级的推荐奖励万分比
+        qualification_threshold: u64,           // 上级获得直推奖励需要的总质押
+        deduct_from_user: bool,                 // 推荐奖励是否从用户奖励中扣除
+    ) -> Result<()> {
+        let staking_instance = &mut ctx.accounts.staking_instance;
+        let referral_config = &mut ctx.accounts.referral_config;
+
+        if !is_authorized(&ctx.accounts.authority.key(), &staking_instance.authority) {
+            return Err(ErrorCode::NotAuthority.into());
+        }
+        if !validate_referral_config(
+            team_levels,
+            &rank_thresholds,
+            &rank_reward_bps,
+            evaluation_period,
+            direct_reward_bps,
+        ) {
+            return Err(ErrorCode::InvalidReferralConfig.into());
+        }
+
+        referral_config.team_levels = team_levels;
+        referral_config.rank_thresholds = rank_thresholds;
+        referral_config.rank_reward_bps = rank_reward_bps;
+        referral_config.evaluation_period = evaluation_period;
+        referral_config.direct_reward_bps = direct_reward_bps;
+        referral_config.qualification_threshold = qualification_threshold;
+        referral_config.deduct_from_user = deduct_from_user;
+        staking_instance.referral_reserve_bps = referral_reserve_bps(referral_config);
+
+        Ok(())
+    }
+
+    // 管理员创建协议根推荐人，没有推荐人的用户绑定到根推荐人
+    pub fn initialize_root_user(ctx: Context<InitializeRootUser>) -> Result<()> {
+        let staking_instance = &mut ctx.accounts.staking_instance;
+        let user_instance = &mut ctx.accounts.user_instance;
+
+        if !is_authorized(&ctx.accounts.authority.key(), &staking_instance.authority) {
+            return Err(ErrorCode::NotAuthority.into());
+        }
+        if staking_instance.root_user != Pubkey::default() {
+            return Err(ErrorCode::InvalidReferralAccount.into());
+        }
+
+        let clock = Clock::get().map_err(|_| ErrorCode::ClockUnavailable)?;
+        user_instance.user_address = ctx.accounts.authority.key();
+        user_instance.total_deposited_amount = 0;
+        user_instance.user_superior_token_account = Pubkey::default(); // 没有上级，不发放推荐奖励
+        user_instance.position_count = 0;
+        user_instance.isinit = true;
+        user_instance.superior = Pubkey::default(); // 根推荐人没有上级
+        user_instance.depth = 0;
+        user_instance.team_volume = 0;
+        user_instance.rank = 0;
+        user_instance.period_volume = 0;
+        user_instance.last_rank_evaluation = clock.unix_timestamp as u64;
+        user_instance.referral_reward_paid = 0;
+        user_instance.referral_owed = 0;
+
+        staking_instance.root_user = user_instance.key();
+
+        Ok(())
+    }
+
+    // 通过推荐人的 User 账户绑定上级。推荐人必须已经存在，而当前用户的 User 账户此时才创建，
+    // 绑定关系又不能修改，所以推荐链中不可能出现当前用户，不会形成循环
     pub fn initialize_user(ctx: Context<InitializeUser>) -> Result<()> {
         let user_instance = &mut ctx.accounts.user_instance;
         let staking_instance = &mut ctx.accounts.staking_instance;
+        let superior_instance = &ctx.accounts.superior_instance;
         if staking_instance.reward_token_mint != ctx.accounts.user_superior_token_account.mint {
             return Err(ErrorCode::MintAccountIsNotMatch.into());
         }
+
+        // 推荐人必须是当前质押实例下已初始化的 User 账户
+        let (expected_superior_address, _bump_seed) = Pubkey::find_program_address(
+            &[
+                crate::USER_SEED.as_ref(),
+                staking_instance.key().as_ref(),
+                superior_instance.user_address.as_ref(),
+            ],
+            ctx.program_id,
+        );
+        if superior_instance.key() != expected_superior_address || !superior_instance.isinit {
+            return Err(ErrorCode::InvalidReferralAccount.into());
+        }
+        // 不能推荐自己
+        if superior_instance.key() == user_instance.key()
+            || superior_instance.user_address == ctx.accounts.authority.key()
+        {
+            return Err(ErrorCode::SelfReferral.into());
+        }
+        // 上级 Token 账户必须属于推荐人
+        if ctx.accounts.user_superior_token_account.owner != superior_instance.user_address {
+            return Err(ErrorCode::UserSuperiorTokenAccountIsNotMatch.into());
+        }
+
         user_instance.user_address = ctx.accounts.authority.key();
         // 初始化 User 结构体的字段
         user_instance.total_deposited_amount = 0; // 初始化为 0，表示用户没有存入任何质押
         user_instance.user_superior_token_account = ctx.accounts.user_superior_token_account.key(); // 设置上级 Token 账户地址
+        user_instance.position_count = 0; // 还没有质押仓位
         user_instance.isinit = true; // 标记为已初始化
 
-        // 初始化 staked_info 数组，所有的质押池信息都设为默认值
-        for staked in user_instance.staked_info.iter_mut() {
-            staked.deposited_amount = 0; // 每个质押池的存入金额初始化为 0
-            staked.reward_debt = 0; // 奖励债务初始化为 0
-            staked.accumulated_reward = 0; // 累计奖励初始化为 0
-            staked.is_staked = false; // 初始时没有质押
-            staked.stake_type = 0; // 默认的质押类型为 0 (3个月质押)
-            staked.stake_start_time = 0; // 初始质押开始时间为 0
-            staked.stake_end_time = 0; // 初始质押结束时间为 0
-            staked.receivedReward = 0; //初始化已领取收益
-            staked.can_cancel_stake = false;
-        }
+        let clock = Clock::get().map_err(|_| ErrorCode::ClockUnavailable)?;
+        user_instance.superior = superior_instance.key();
+        user_instance.depth = superior_instance
+            .depth
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+        user_instance.team_volume = 0;
+        user_instance.rank = 0;
+        user_instance.period_volume = 0;
+        user_instance.last_rank_evaluation = clock.unix_timestamp as u64;
+        user_instance.referral_reward_paid = 0;
+        user_instance.referral_owed = 0;
+
+        emit!(ReferrerBound {
+            user: user_instance.key(),
+            superior: user_instance.superior,
+            depth: user_instance.depth,
+        });
 
         Ok(())
     }
 
-    // 0 = 3m,1= 6m,2= 12m;
-    pub fn enter_staking(
-        ctx: Context<EnterStaking>,
+    // 质押池由 pool 账户指定，每次质押创建一个新的 position 账户
+    // remaining_accounts 依次传入上级、上上级……的 User 账户（最多 team_levels 层），用于累加团队业绩
+    pub fn enter_staking<'info>(
+        ctx: Context<'_, '_, '_, 'info, EnterStaking<'info>>,
         lp_staking_number: u64, // 用户要质押的 LP Token 数量
-        stake_type: u64,        // 用户选择的质押池类型
-        staked_info_index: u64, // 用户选择的 staked_info 索引
     ) -> Result<()> {
         // 获取账户实例
         let staking_instance = &mut ctx.accounts.staking_instance;
+        let pool = &mut ctx.accounts.pool;
         let user_instance = &mut ctx.accounts.user_instance;
+        let position = &mut ctx.accounts.position;
         let user_lp_token_account = &ctx.accounts.user_lp_token_account;
         let gdtc_lp_in_account = &ctx.accounts.gdtc_lp_in_account;
 
@@ -276,11 +927,6 @@ pub mod gdtc_staking {
             ],
             program_id,
         );
-        msg!(
-            "expected_user_address is: {},user_instance: {}",
-            expected_user_address.key(),
-            user_instance.key()
-        );
         // 确保 staking_instance 是由合约程序派生的
         if user_instance.key() != expected_user_address {
             return Err(ErrorCode::InvalidUserInstance.into());
@@ -300,43 +946,18 @@ pub mod gdtc_staking {
         if user_lp_token_account.amount < lp_staking_number {
             return Err(ErrorCode::TokenAccountBalanceInsufficient.into());
         }
-        if staked_info_index > 9 {
-            return Err(ErrorCode::InvalidStakedInfoIndex.into());
-        }
 
-        if stake_type > 2 {
+        // 验证质押池属于当前质押实例
+        if pool.staking_instance != staking_instance.key() {
             return Err(ErrorCode::InvalidStakeType.into());
         }
         // 获取当前时间戳并计算质押结束时间
         let current_timestamp = clock.unix_timestamp as u64;
 
-        msg!("staking time is :{}", current_timestamp);
-        let is_end = match stake_type {
-            0 => {
-                if current_timestamp > 2358810461 {
-                    true
-                } else {
-                    false
-                }
-            }
-            1 => {
-                if current_timestamp > 2350861661 {
-                    true
-                } else {
-                    false
-                }
-            }
-            2 => {
-                if current_timestamp > 2335136861 {
-                    true
-                } else {
-                    false
-                }
-            }
-            _ => true, // 对于无效的 stake_type，直接认为已经结束
-        };
-
-        if is_end {
+        if current_timestamp < pool.staking_open_from {
+            return Err(ErrorCode::StakingNotOpen.into());
+        }
+        if current_timestamp > pool.staking_closes_at {
             return Err(ErrorCode::StakingEnded.into());
         }
 
@@ -345,18 +966,7 @@ pub mod gdtc_staking {
         //     .checked_add(lp_staking_number)
         //     .ok_or(ErrorCode::Overflow)?;
 
-        // 获取质押信息
-        let index = staked_info_index as usize;
-        // 如果已经质押，报错
-        if user_instance.staked_info[index].is_staked {
-            return Err(ErrorCode::UserAlreadyStaked.into());
-        }
-        // 验证用户选择的质押池类型是否有效
-        if stake_type >= staking_instance.pools.len() as u64 {
-            return Err(ErrorCode::InvalidStakeType.into());
-        }
-
-        let stake_end_time = generate_release_timestamps(current_timestamp, stake_type);
+        let stake_end_time = generate_release_timestamps(current_timestamp, pool.duration_months);
 
         // 更新用户账户
         user_instance.total_deposited_amount = user_instance
@@ -364,29 +974,58 @@ pub mod gdtc_staking {
             .checked_add(lp_staking_number)
             .ok_or(ErrorCode::Overflow)?;
 
-        let staked_info = &mut user_instance.staked_info[index];
-
-        staked_info.deposited_amount = staked_info
-            .deposited_amount
-            .checked_add(lp_staking_number)
+        // 初始化质押仓位，nonce 单调递增
+        position.user_instance = user_instance.key();
+        position.nonce = user_instance.position_count;
+        position.pool = pool.key();
+        position.deposited_amount = lp_staking_number;
+        position.reward_debt = 0;
+        position.accumulated_reward = 0;
+        position.stake_start_time = current_timestamp;
+        position.stake_end_time = stake_end_time;
+        position.received_reward = 0;
+        position.can_cancel_stake = false;
+        position.reward_owed = 0;
+        position.referral_owed = 0;
+
+        user_instance.position_count = user_instance
+            .position_count
+            .checked_add(1)
             .ok_or(ErrorCode::Overflow)?;
-        staked_info.stake_type = stake_type;
-        staked_info.is_staked = true;
-        staked_info.stake_start_time = current_timestamp;
-        staked_info.stake_end_time = stake_end_time;
+
+        // 先按加入前的份额结算奖励池，新份额不分享加入之前的奖励
+        update_reward_pool(current_timestamp, staking_instance, pool);
 
         // 更新质押池的总份额
-        let pool = &mut staking_instance.pools[stake_type as usize];
         pool.total_shares = pool
             .total_shares
             .checked_add(lp_staking_number)
             .ok_or(ErrorCode::Overflow)?;
 
-        // 更新奖励池
-        update_reward_pool(current_timestamp, staking_instance);
+        // 更新仓位奖励债务
+        update_reward_debt(pool, position);
 
-        // 更新用户奖励债务
-        update_reward_debt(staking_instance, user_instance, staked_info_index);
+        // 沿推荐关系向上累加 team_levels 层的团队业绩
+        let referral_config = &ctx.accounts.referral_config;
+        let mut ancestors = ctx.remaining_accounts.iter();
+        let mut superior = user_instance.superior;
+        for _ in 0..referral_config.team_levels {
+            if superior == Pubkey::default() || superior == user_instance.key() {
+                break;
+            }
+            let ancestor_info = ancestors.next().ok_or(ErrorCode::InvalidReferralAccount)?;
+            if ancestor_info.key() != superior {
+                return Err(ErrorCode::InvalidReferralAccount.into());
+            }
+            // 上级尚未创建 User 账户时停止
+            if ancestor_info.owner != ctx.program_id {
+                break;
+            }
+            let mut ancestor: Account<User> = Account::try_from(ancestor_info)?;
+            add_team_volume(referral_config, &mut ancestor, lp_staking_number, current_timestamp)?;
+            superior = ancestor.superior;
+            ancestor.exit(ctx.program_id)?;
+        }
 
         // 转移 LP Token 到合约的 Vault
         token::transfer(
@@ -397,10 +1036,16 @@ pub mod gdtc_staking {
         Ok(())
     }
 
-    pub fn cancel_staking(ctx: Context<CancelStaking>, staked_info_index: u64) -> Result<()> {
+    // 解除质押后关闭 position 账户，租金退还给用户；仓位还有未领取或欠付的奖励时暂不关闭
+    // remaining_accounts 与 enter_staking 相同，依次传入上级、上上级……的 User 账户，用于扣减团队业绩
+    pub fn cancel_staking<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelStaking<'info>>,
+    ) -> Result<()> {
         // 获取相关账户
         let staking_instance = &mut ctx.accounts.staking_instance;
+        let pool = &mut ctx.accounts.pool;
         let user_instance = &mut ctx.accounts.user_instance;
+        let position = &mut ctx.accounts.position;
         let user_lp_token_account = &mut ctx.accounts.user_lp_token_account;
         let gdtc_lp_in_account = &ctx.accounts.gdtc_lp_in_account;
 
@@ -422,11 +1067,6 @@ pub mod gdtc_staking {
             ],
             program_id,
         );
-        msg!(
-            "expected_user_address is: {},user_instance: {}",
-            expected_user_address.key(),
-            user_instance.key()
-        );
         // 确保 staking_instance 是由合约程序派生的
         if user_instance.key() != expected_user_address {
             return Err(ErrorCode::InvalidUserInstance.into());
@@ -442,32 +1082,34 @@ pub mod gdtc_staking {
             return Err(ErrorCode::UserAccountIsNotMatch.into());
         }
 
-        let index = staked_info_index as usize;
+        let amount = position.deposited_amount;
 
-        let amount = user_instance.staked_info[index].deposited_amount;
-
-        // 检查用户是否有质押
-        if !user_instance.staked_info[index].is_staked {
+        // 检查仓位属于该用户
+        if position.user_instance != user_instance.key() {
             return Err(ErrorCode::NoStakingToCancel.into());
         }
+        // 检查质押池与质押记录一致
+        if position.pool != pool.key() {
+            return Err(ErrorCode::InvalidStakeType.into());
+        }
 
         // 获取当前时间戳
         let clock = Clock::get().map_err(|_| ErrorCode::ClockUnavailable)?;
         let current_timestamp = clock.unix_timestamp as u64;
 
         // 检查质押是否到期
-        if current_timestamp < user_instance.staked_info[index].stake_end_time {
+        if !can_unstake(position, current_timestamp) {
             return Err(ErrorCode::StakingNotMatured.into());
         }
-        if !user_instance.staked_info[index].can_cancel_stake {
+        if !position.can_cancel_stake {
             return Err(ErrorCode::NeedCliamRewards.into());
         }
 
         // 更新奖励池并计算用户的奖励
-        update_reward_pool(current_timestamp, staking_instance);
+        update_reward_pool(current_timestamp, staking_instance, pool);
 
         // 存储用户的待领取奖励
-        store_pending_reward(staking_instance, user_instance, staked_info_index)?;
+        store_pending_reward(pool, position)?;
 
         // 获取 PDA 签名者
         let bump_seed = ctx.bumps.pda_account;
@@ -496,42 +1138,65 @@ pub mod gdtc_staking {
         )?;
 
         // // 更新质押池的总份额
-        let pool =
-            &mut staking_instance.pools[user_instance.staked_info[index].stake_type as usize];
-
         pool.total_shares = pool
             .total_shares
             .checked_sub(amount)
             .ok_or(ErrorCode::Underflow)?;
 
-        // 更新奖励债务
-        update_reward_debt(staking_instance, user_instance, staked_info_index);
-
-        // 获取用户对应的质押信息
-        let staked_info = &mut user_instance.staked_info[index];
-        // 重置用户的质押状态
-        staked_info.deposited_amount = 0;
-        staked_info.accumulated_reward = 0;
-        staked_info.is_staked = false; // 标记用户未质押
-        staked_info.stake_type = 0;
-        staked_info.reward_debt = 0; // 重置奖励债务
-        staked_info.stake_start_time = 0; // 重置质押开始时间
-        staked_info.stake_end_time = 0; // 重置质押结束时间
-        staked_info.receivedReward = 0;
-        user_instance.staked_info[index].can_cancel_stake = false;
+        // 已经返还的 LP 不再产生奖励
+        position.deposited_amount = 0;
+        update_reward_debt(pool, position);
+
+        // 沿推荐关系向上扣减 team_levels 层的团队业绩
+        let referral_config = &ctx.accounts.referral_config;
+        let mut ancestors = ctx.remaining_accounts.iter();
+        let mut superior = user_instance.superior;
+        for _ in 0..referral_config.team_levels {
+            if superior == Pubkey::default() || superior == user_instance.key() {
+                break;
+            }
+            let ancestor_info = ancestors.next().ok_or(ErrorCode::InvalidReferralAccount)?;
+            if ancestor_info.key() != superior {
+                return Err(ErrorCode::InvalidReferralAccount.into());
+            }
+            if ancestor_info.owner != ctx.program_id {
+                break;
+            }
+            let mut ancestor: Account<User> = Account::try_from(ancestor_info)?;
+            remove_team_volume(referral_config, &mut ancestor, amount, current_timestamp);
+            superior = ancestor.superior;
+            ancestor.exit(ctx.program_id)?;
+        }
 
+        // 没有未领取和欠付的奖励时关闭 position 账户，租金退还给用户；
+        // 上次领取之后产生的奖励已经计入 total_emitted 并为推荐奖励预留，保留仓位通过 claim_rewards 按正常流程领取，
+        // 欠付奖励同样在补充奖励后领取，结清后再次调用即可关闭
+        if position.accumulated_reward == 0
+            && position.reward_owed == 0
+            && position.referral_owed == 0
+        {
+            ctx.accounts
+                .position
+                .close(ctx.accounts.authority.to_account_info())?;
+        }
         Ok(())
     }
 
-    pub fn claim_rewards(ctx: Context<ClaimRewards>, staked_info_index: u64) -> Result<()> {
+    // remaining_accounts 依次传入上上级、再上一级……的 User 账户（可写）和对应接收奖励的 Token 账户（可写），用于按级差发放等级奖励
+    pub fn claim_rewards<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimRewards<'info>>,
+    ) -> Result<()> {
         // 获取账户实例
         let staking_instance = &mut ctx.accounts.staking_instance;
+        let pool = &mut ctx.accounts.pool;
         let user_instance = &mut ctx.accounts.user_instance;
-        let super_instance = &ctx.accounts.super_instance;
+        let position = &mut ctx.accounts.position;
+        let super_instance = &mut ctx.accounts.super_instance;
+        let referral_config = &ctx.accounts.referral_config;
 
         // let user_gdtc_token_account = &mut ctx.accounts.user_gdtc_token_account;
         let gdtc_reward_out_account = &ctx.accounts.gdtc_reward_out_account;
-        let user_super_gdtc_token_account = &mut ctx.accounts.user_super_gdtc_token_account;
+        let user_super_gdtc_token_account = &ctx.accounts.user_super_gdtc_token_account;
 
         let program_id = ctx.program_id; // 获取当前合约的程序ID
                                          // 计算 staking_instance 的派生地址
@@ -559,33 +1224,46 @@ pub mod gdtc_staking {
             ],
             program_id,
         );
-        msg!(
-            "expected_user_address is: {},user_instance: {}",
-            expected_user_address.key(),
-            user_instance.key()
-        );
         // 确保 staking_instance 是由合约程序派生的
         if user_instance.key() != expected_user_address {
             return Err(ErrorCode::InvalidUserInstance.into());
         }
 
-        //用户上级账户验证
-        let (expected_user_superior_address, bump_seed) = Pubkey::find_program_address(
-            &[
-                crate::USER_SEED.as_ref(),
-                staking_instance.key().as_ref(),
-                user_super_gdtc_token_account.owner.key().as_ref(),
-            ],
-            program_id,
-        );
-        msg!(
-            "expected_user_superior_address is: {},user_instance: {}",
-            expected_user_superior_address.key(),
-            super_instance.key()
-        );
-        // 确保 staking_instance 是由合约程序派生的
-        if super_instance.key() != expected_user_superior_address {
-            return Err(ErrorCode::InvalidUserInstance.into());
+        // 根推荐人没有上级，不发放推荐奖励，也不需要传入上级账户；其他用户必须传入绑定的上级账户
+        let has_superior = user_instance.superior != Pubkey::default();
+        if has_superior {
+            let super_account = super_instance
+                .as_ref()
+                .ok_or(ErrorCode::InvalidReferralAccount)?;
+            let super_token_account = user_super_gdtc_token_account
+                .as_ref()
+                .ok_or(ErrorCode::InvalidReferralAccount)?;
+            // 上级不能是用户自己
+            if super_account.key() == user_instance.key() {
+                return Err(ErrorCode::SelfReferral.into());
+            }
+            if super_account.key() != user_instance.superior {
+                return Err(ErrorCode::InvalidReferralAccount.into());
+            }
+
+            //用户上级账户验证
+            let (expected_user_superior_address, bump_seed) = Pubkey::find_program_address(
+                &[
+                    crate::USER_SEED.as_ref(),
+                    staking_instance.key().as_ref(),
+                    super_token_account.owner.key().as_ref(),
+                ],
+                program_id,
+            );
+            if super_account.key() != expected_user_superior_address {
+                return Err(ErrorCode::InvalidUserInstance.into());
+            }
+            if super_account.user_address != super_token_account.owner.key() {
+                return Err(ErrorCode::UserAccountIsNotMatch.into());
+            }
+            if user_instance.user_superior_token_account != super_token_account.key() {
+                return Err(ErrorCode::MintAccountIsNotMatch.into());
+            }
         }
 
         if staking_instance.reward_token_mint != gdtc_reward_out_account.mint {
@@ -595,74 +1273,156 @@ pub mod gdtc_staking {
         // 获取当前时间戳
         let clock = Clock::get().map_err(|_| ErrorCode::ClockUnavailable)?;
         let current_timestamp = clock.unix_timestamp as u64;
-        let index = staked_info_index as usize;
         if user_instance.user_address != ctx.accounts.user_gdtc_token_account.owner.key() {
             return Err(ErrorCode::UserAccountIsNotMatch.into());
         }
-        if super_instance.user_address != user_super_gdtc_token_account.owner.key() {
-            return Err(ErrorCode::UserAccountIsNotMatch.into());
-        }
 
-        // 检查用户是否有质押
-        if !user_instance.staked_info[index].is_staked {
-            msg!(
-                "index: {},staked:{}",
-                staked_info_index,
-                user_instance.staked_info[index].is_staked
-            );
+        // 检查仓位属于该用户
+        if position.user_instance != user_instance.key() {
             return Err(ErrorCode::NoStakingToClaimRewards.into());
-            // return Ok(());
         }
-        msg!(
-            "index{},staked:{}",
-            index,
-            user_instance.staked_info[index].is_staked
-        );
-        if user_instance.user_superior_token_account != user_super_gdtc_token_account.key() {
-            return Err(ErrorCode::MintAccountIsNotMatch.into());
+        // 检查质押池与质押记录一致
+        if position.pool != pool.key() {
+            return Err(ErrorCode::InvalidStakeType.into());
         }
 
         // 更新奖励池并计算用户的奖励
-        update_reward_pool(current_timestamp, staking_instance);
+        update_reward_pool(current_timestamp, staking_instance, pool);
 
-        store_pending_reward(staking_instance, user_instance, staked_info_index)?;
+        store_pending_reward(pool, position)?;
 
         // 更新用户的奖励债务
-        // update_reward_debt(staking_instance, user_instance, staked_info_index);
+        // update_reward_debt(pool, position);
 
-        // 计算用户的奖励
-        let mut accumulated_reward = user_instance.staked_info[index].accumulated_reward;
-        if accumulated_reward == 0 {
+        // 计算用户的奖励，包括之前奖励账户余额不足时欠付的部分
+        let accumulated_reward = position.accumulated_reward;
+        let owed_before = position
+            .reward_owed
+            .checked_add(position.referral_owed)
+            .ok_or(ErrorCode::Overflow)?;
+        // 到期后解除质押之前仓位仍然计入份额并继续产生奖励，同样可以领取
+        if accumulated_reward == 0 && owed_before == 0 {
             return Err(ErrorCode::NoRewardsToClaim.into());
         }
 
-        // 检查奖励账户余额是否足够
-        if gdtc_reward_out_account.amount < accumulated_reward {
-            if current_timestamp >= user_instance.staked_info[index].stake_end_time
-                && user_instance.user_address == ctx.accounts.authority.key()
-            {
-                if user_instance.staked_info[index].can_cancel_stake != true {
-                    user_instance.staked_info[index].can_cancel_stake = true;
-                    user_instance.total_deposited_amount = user_instance
-                        .total_deposited_amount
-                        .checked_sub(user_instance.staked_info[index].deposited_amount)
-                        .ok_or(ErrorCode::Overflow)?;
+        // 重新评估上级的等级，再按推荐配置拆分用户和上级的奖励；没有上级时奖励全部归用户
+        let (mut user_reward, mut superior_reward) = match super_instance.as_mut() {
+            Some(super_account) if has_superior => {
+                evaluate_rank(referral_config, super_account, current_timestamp);
+                split_referral_reward(referral_config, super_account, accumulated_reward)
+            }
+            _ => (accumulated_reward, 0),
+        };
+
+        // 更上层的推荐人最多到 team_levels 层，按级差获得等级奖励，直接上级的等级比例视为已经发放；
+        // 已经发放到最高等级比例后不再向上查找，之后的账户可以不传
+        let max_rank_bps = referral_config
+            .rank_reward_bps
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0);
+        let mut ancestors: Vec<(Account<'info, User>, AccountInfo<'info>, u64)> = Vec::new();
+        if let Some(super_account) = super_instance.as_ref().filter(|_| has_superior) {
+            let mut paid_bps = rank_reward_bps(referral_config, super_account.rank);
+            let mut next = super_account.superior;
+            let mut next_token_account = super_account.user_superior_token_account;
+            let mut remaining = ctx.remaining_accounts.iter();
+            for _ in 1..referral_config.team_levels {
+                if next == Pubkey::default() || paid_bps >= max_rank_bps {
+                    break;
+                }
+                let ancestor_info = remaining.next().ok_or(ErrorCode::InvalidReferralAccount)?;
+                let token_info = remaining.next().ok_or(ErrorCode::InvalidReferralAccount)?;
+                // 接收奖励的 Token 账户是下一层绑定推荐关系时记录的上级 Token 账户
+                if ancestor_info.key() != next || token_info.key() != next_token_account {
+                    return Err(ErrorCode::InvalidReferralAccount.into());
                 }
+                let mut ancestor: Account<User> = Account::try_from(ancestor_info)?;
+                evaluate_rank(referral_config, &mut ancestor, current_timestamp);
+                let mut reward = differential_rank_reward(
+                    referral_config,
+                    ancestor.rank,
+                    &mut paid_bps,
+                    accumulated_reward,
+                );
+                // 扣除模式下同样从用户奖励中扣除，最多扣完
+                if referral_config.deduct_from_user {
+                    reward = reward.min(user_reward);
+                    user_reward -= reward;
+                }
+                next = ancestor.superior;
+                next_token_account = ancestor.user_superior_token_account;
+                ancestors.push((ancestor, token_info.clone(), reward));
             }
-            return Ok(());
         }
+        let referral_reward = ancestors
+            .iter()
+            .try_fold(superior_reward, |total, (_, _, reward)| total.checked_add(*reward))
+            .ok_or(ErrorCode::Overflow)?;
+
+        // 释放本次奖励排放时为推荐奖励预留的部分；额外发放的推荐奖励同样计入 total_emitted，
+        // 不能超过已注入但尚未排放的奖励。调高推荐比例后预留和注入都不足时用户照常领取，
+        // 推荐奖励按直接上级、更上层推荐人的顺序削减到可用的注入为止，削减的部分不再补发
+        staking_instance.referral_reserve = staking_instance
+            .referral_reserve
+            .saturating_sub(referral_reserve_for(staking_instance, accumulated_reward));
+        if !referral_config.deduct_from_user && referral_reward > 0 {
+            let mut fundable = unemitted_funding(staking_instance);
+            superior_reward = superior_reward.min(fundable);
+            fundable -= superior_reward;
+            for (_, _, reward) in ancestors.iter_mut() {
+                *reward = (*reward).min(fundable);
+                fundable -= *reward;
+            }
+            let funded_reward = ancestors
+                .iter()
+                .fold(superior_reward, |total, (_, _, reward)| total + *reward);
+            if funded_reward < referral_reward {
+                emit!(ReferralRewardCapped {
+                    position: position.key(),
+                    referral_reward,
+                    funded_reward,
+                });
+            }
+            staking_instance.total_emitted = staking_instance
+                .total_emitted
+                .checked_add(funded_reward)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+
+        // 本次应付 = 新产生的奖励 + 之前欠付的奖励
+        let user_due = position
+            .reward_owed
+            .checked_add(user_reward)
+            .ok_or(ErrorCode::Overflow)?;
+        let superior_due = position
+            .referral_owed
+            .checked_add(superior_reward)
+            .ok_or(ErrorCode::Overflow)?;
+
+        // 奖励账户余额不足时按现有余额先付用户、再付上级，其余记为欠付
+        let available = gdtc_reward_out_account.amount;
+        let user_paid = user_due.min(available);
+        let superior_paid = superior_due.min(available - user_paid);
 
         let bump_seed = ctx.bumps.pda_account;
         let signer_seeds: &[&[&[u8]]] = &[&[crate::LPTOKEN_SEED.as_ref(), &[bump_seed]]];
 
-        if super_instance.total_deposited_amount > 2000000000 {
+        if superior_paid > 0 {
+            let super_account = super_instance
+                .as_mut()
+                .ok_or(ErrorCode::InvalidReferralAccount)?;
+            let super_token_account = user_super_gdtc_token_account
+                .as_ref()
+                .ok_or(ErrorCode::InvalidReferralAccount)?;
             let transfer_instruction = spl_token::instruction::transfer(
                 &ctx.accounts.token_program.key(),
                 &ctx.accounts.gdtc_reward_out_account.key(),
-                &ctx.accounts.user_super_gdtc_token_account.key(),
+                &super_token_account.key(),
                 &ctx.accounts.pda_account.key(),
                 &[],
-                accumulated_reward / 10,
+                superior_paid,
             )?;
 
             // 执行带签名的 CPI 调用
@@ -671,60 +1431,131 @@ pub mod gdtc_staking {
                 &[
                     ctx.accounts.token_program.to_account_info(),
                     ctx.accounts.gdtc_reward_out_account.to_account_info(),
-                    ctx.accounts.user_super_gdtc_token_account.to_account_info(),
+                    super_token_account.to_account_info(),
                     ctx.accounts.pda_account.to_account_info(),
                 ],
                 signer_seeds,
             )?;
-            //取消上级百分之十从挖矿者奖励中拿出
-            // accumulated_reward = accumulated_reward - (accumulated_reward / 10);
+
+            super_account.referral_reward_paid = super_account
+                .referral_reward_paid
+                .checked_add(superior_paid)
+                .ok_or(ErrorCode::Overflow)?;
         }
 
-        // 生成从 GDTC 托管账户到用户 LP Token 账户的转账指令
-        let transfer_instruction = spl_token::instruction::transfer(
-            &ctx.accounts.token_program.key(),
-            &ctx.accounts.gdtc_reward_out_account.key(),
-            &ctx.accounts.user_gdtc_token_account.key(),
-            &ctx.accounts.pda_account.key(),
-            &[],
-            accumulated_reward,
-        )?;
+        if user_paid > 0 {
+            // 生成从 GDTC 托管账户到用户 GDTC Token 账户的转账指令
+            let transfer_instruction = spl_token::instruction::transfer(
+                &ctx.accounts.token_program.key(),
+                &ctx.accounts.gdtc_reward_out_account.key(),
+                &ctx.accounts.user_gdtc_token_account.key(),
+                &ctx.accounts.pda_account.key(),
+                &[],
+                user_paid,
+            )?;
 
-        // 执行带签名的 CPI 调用
-        invoke_signed(
-            &transfer_instruction,
-            &[
-                ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.gdtc_reward_out_account.to_account_info(),
-                ctx.accounts.user_gdtc_token_account.to_account_info(),
-                ctx.accounts.pda_account.to_account_info(),
-            ],
-            signer_seeds,
-        )?;
+            // 执行带签名的 CPI 调用
+            invoke_signed(
+                &transfer_instruction,
+                &[
+                    ctx.accounts.token_program.to_account_info(),
+                    ctx.accounts.gdtc_reward_out_account.to_account_info(),
+                    ctx.accounts.user_gdtc_token_account.to_account_info(),
+                    ctx.accounts.pda_account.to_account_info(),
+                ],
+                signer_seeds,
+            )?;
+        }
 
-        if current_timestamp >= user_instance.staked_info[index].stake_end_time {
-            if user_instance.staked_info[index].can_cancel_stake == true {
-                msg!(
-                    "can_cancel_stake,{},index : {}",
-                    user_instance.staked_info[index].can_cancel_stake,
-                    index
-                );
-                return Err(ErrorCode::NoRewardsToClaim.into());
+        // 更上层推荐人的等级奖励在用户和直接上级之后支付，余额不足时记入该推荐人 User 账户的 referral_owed，
+        // 下次作为上层推荐人获得等级奖励时一并支付
+        let mut remaining_balance = available - user_paid - superior_paid;
+        for (ancestor, token_info, reward) in ancestors.iter_mut() {
+            let ancestor_owed = ancestor.referral_owed;
+            let ancestor_due = ancestor_owed
+                .checked_add(*reward)
+                .ok_or(ErrorCode::Overflow)?;
+            let ancestor_paid = ancestor_due.min(remaining_balance);
+            remaining_balance -= ancestor_paid;
+
+            if ancestor_paid > 0 {
+                let transfer_instruction = spl_token::instruction::transfer(
+                    &ctx.accounts.token_program.key(),
+                    &ctx.accounts.gdtc_reward_out_account.key(),
+                    &token_info.key(),
+                    &ctx.accounts.pda_account.key(),
+                    &[],
+                    ancestor_paid,
+                )?;
+
+                // 执行带签名的 CPI 调用
+                invoke_signed(
+                    &transfer_instruction,
+                    &[
+                        ctx.accounts.token_program.to_account_info(),
+                        ctx.accounts.gdtc_reward_out_account.to_account_info(),
+                        token_info.clone(),
+                        ctx.accounts.pda_account.to_account_info(),
+                    ],
+                    signer_seeds,
+                )?;
+
+                ancestor.referral_reward_paid = ancestor
+                    .referral_reward_paid
+                    .checked_add(ancestor_paid)
+                    .ok_or(ErrorCode::Overflow)?;
             }
-            user_instance.staked_info[index].can_cancel_stake = true;
-            user_instance.total_deposited_amount = user_instance
-                .total_deposited_amount
-                .checked_sub(user_instance.staked_info[index].deposited_amount)
+
+            ancestor.referral_owed = ancestor_due - ancestor_paid;
+            staking_instance.outstanding_rewards = staking_instance
+                .outstanding_rewards
+                .checked_sub(ancestor_owed)
+                .ok_or(ErrorCode::Underflow)?
+                .checked_add(ancestor.referral_owed)
                 .ok_or(ErrorCode::Overflow)?;
+            ancestor.exit(ctx.program_id)?;
         }
 
-        // 重置用户累计奖励
-        user_instance.staked_info[index].accumulated_reward = 0;
+        // 重置用户累计奖励，未支付的部分记为欠付
+        position.accumulated_reward = 0;
+        position.reward_owed = user_due - user_paid;
+        position.referral_owed = superior_due - superior_paid;
+        position.received_reward = position
+            .received_reward
+            .checked_add(user_paid)
+            .ok_or(ErrorCode::Overflow)?;
 
-        user_instance.staked_info[index].receivedReward = user_instance.staked_info[index]
-            .receivedReward
-            .checked_add(accumulated_reward)
+        // 更新协议的欠付奖励总额
+        let owed_after = position
+            .reward_owed
+            .checked_add(position.referral_owed)
+            .ok_or(ErrorCode::Overflow)?;
+        staking_instance.outstanding_rewards = staking_instance
+            .outstanding_rewards
+            .checked_sub(owed_before)
+            .ok_or(ErrorCode::Underflow)?
+            .checked_add(owed_after)
             .ok_or(ErrorCode::Overflow)?;
+
+        if owed_after > 0 {
+            emit!(PartialRewardPayout {
+                position: position.key(),
+                user_paid,
+                reward_owed: position.reward_owed,
+                referral_paid: superior_paid,
+                referral_owed: position.referral_owed,
+                outstanding_rewards: staking_instance.outstanding_rewards,
+            });
+        }
+
+        // 到期后标记可以解除质押
+        if current_timestamp >= position.stake_end_time && !position.can_cancel_stake {
+            position.can_cancel_stake = true;
+            user_instance.total_deposited_amount = user_instance
+                .total_deposited_amount
+                .checked_sub(position.deposited_amount)
+                .ok_or(ErrorCode::Overflow)?;
+        }
         Ok(())
     }
 
@@ -791,4 +1622,87 @@ pub enum ErrorCode {
 
     #[msg("Pda address  does not match.")]
     PdaAccountIsNotMatch,
-}
\ No newline at end of file
+
+    #[msg("Only the staking authority can perform this action.")]
+    NotAuthority,
+
+    #[msg("Invalid pool configuration.")]
+    InvalidPoolConfig,
+
+    #[msg("The staking has not opened for this pool yet.")]
+    StakingNotOpen,
+
+    #[msg("Total emission cap exceeds the funded reward allocation.")]
+    EmissionCapExceedsFunding,
+
+    #[msg("Invalid referral configuration.")]
+    InvalidReferralConfig,
+
+    #[msg("Referral account does not match the user's superior.")]
+    InvalidReferralAccount,
+
+    #[msg("Users cannot refer themselves.")]
+    SelfReferral,
+
+    #[msg("Pool accounts must be this staking instance's pool PDAs, passed in pool_id order.")]
+    InvalidPoolAccount,
+}
+
+#[event]
+pub struct PoolCreated {
+    pub pool: Pubkey,             // 质押池账户
+    pub pool_id: u64,             // 质押池编号
+    pub duration_months: u64,     // 锁仓月数
+    pub reward_per_sec: u64,      // 第 0 个排放周期的每秒奖励代币数量
+    pub staking_open_from: u64,   // 开始接受质押的时间戳
+    pub staking_closes_at: u64,   // 停止接受质押的时间戳
+    pub rewards_end_at: u64,      // 停止产生奖励的时间戳
+    pub emission_cap: u64,        // 该池最多排放的奖励总量
+}
+
+#[event]
+pub struct PoolScheduleUpdated {
+    pub pool: Pubkey,             // 质押池账户
+    pub staking_open_from: u64,   // 开始接受质押的时间戳
+    pub staking_closes_at: u64,   // 停止接受质押的时间戳
+    pub rewards_end_at: u64,      // 停止产生奖励的时间戳
+}
+
+#[event]
+pub struct RewardRateUpdated {
+    pub pool: Pubkey,      // 质押池账户
+    pub old_rate: u64,     // 调整前的每秒奖励代币数量
+    pub new_rate: u64,     // 调整后的每秒奖励代币数量
+    pub effective_at: u64, // 新速率生效的时间戳
+}
+
+#[event]
+pub struct ReferrerBound {
+    pub user: Pubkey,     // 用户的 User 账户
+    pub superior: Pubkey, // 绑定的推荐人的 User 账户
+    pub depth: u64,       // 用户在推荐树中的深度
+}
+
+#[event]
+pub struct PartialRewardPayout {
+    pub position: Pubkey,         // 质押仓位
+    pub user_paid: u64,           // 本次支付给用户的奖励
+    pub reward_owed: u64,         // 仍欠用户的奖励
+    pub referral_paid: u64,       // 本次支付给上级的推荐奖励
+    pub referral_owed: u64,       // 仍欠上级的推荐奖励
+    pub outstanding_rewards: u64, // 协议欠付奖励总额
+}
+
+#[event]
+pub struct ReferralRewardCapped {
+    pub position: Pubkey,     // 质押仓位
+    pub referral_reward: u64, // 本次按推荐配置应发放的推荐奖励
+    pub funded_reward: u64,   // 可用的注入实际覆盖并发放的推荐奖励
+}
+
+#[event]
+pub struct RewardsFunded {
+    pub funder: Pubkey,     // 注入奖励的账户
+    pub amount: u64,        // 本次注入的数量
+    pub total_funded: u64,  // 累计注入的奖励总量
+}
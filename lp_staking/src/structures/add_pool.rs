@@ -0,0 +1,23 @@
+use super::{StakingInstance, StakingPool};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct AddPool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub staking_instance: Account<'info, StakingInstance>,
+    #[account(
+        init,
+        seeds = [
+            crate::POOL_SEED.as_ref(),
+            staking_instance.key().as_ref(),
+            staking_instance.pool_count.to_le_bytes().as_ref()
+        ],
+        bump,
+        space = 8 + core::mem::size_of::<StakingPool>(),
+        payer = authority
+    )]
+    pub pool: Account<'info, StakingPool>,
+    pub system_program: Program<'info, System>,
+}
@@ -1,5 +1,5 @@
-use super::StakingInstance;
-use super::User;
+use super::{ReferralConfig, StakingInstance, StakingPool};
+use super::{Position, User};
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Token, TokenAccount};
 
@@ -9,9 +9,18 @@ pub struct CancelStaking<'info> {
     pub authority: Signer<'info>,
     #[account(mut)]
     pub staking_instance: Account<'info, StakingInstance>,
+    #[account(
+        seeds = [crate::REFERRAL_CONFIG_SEED.as_ref(), staking_instance.key().as_ref()],
+        bump,
+    )]
+    pub referral_config: Account<'info, ReferralConfig>, // 推荐等级配置，用于扣减团队业绩
+    #[account(mut)]
+    pub pool: Account<'info, StakingPool>,
     #[account(mut)]
     pub user_instance: Account<'info, User>,
     #[account(mut)]
+    pub position: Account<'info, Position>, // 解除质押且没有欠付奖励时关闭，租金退还给用户
+    #[account(mut)]
     pub user_lp_token_account: Account<'info, TokenAccount>,
     #[account(mut)]
     pub gdtc_lp_in_account: Account<'info, TokenAccount>,
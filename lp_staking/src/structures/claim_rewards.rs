@@ -1,5 +1,5 @@
-use super::StakingInstance;
-use super::User;
+use super::{ReferralConfig, StakingInstance, StakingPool};
+use super::{Position, User};
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Token, TokenAccount};
 
@@ -9,12 +9,21 @@ pub struct ClaimRewards<'info> {
     pub authority: Signer<'info>, //签名用户
     #[account(mut)]
     pub staking_instance: Account<'info, StakingInstance>, //程序状态账户
+    #[account(
+        seeds = [crate::REFERRAL_CONFIG_SEED.as_ref(), staking_instance.key().as_ref()],
+        bump,
+    )]
+    pub referral_config: Account<'info, ReferralConfig>, // 推荐等级配置
+    #[account(mut)]
+    pub pool: Account<'info, StakingPool>, //质押池账户
     #[account(mut)]
     pub user_instance: Box<Account<'info, User>>, // 用户状态账户
     #[account(mut)]
-    pub super_instance: Box<Account<'info, User>>, //上级状态账户
+    pub position: Account<'info, Position>, // 用户的质押仓位
+    #[account(mut)]
+    pub super_instance: Option<Box<Account<'info, User>>>, //上级状态账户，根推荐人没有上级时不传
     #[account(mut)]
-    pub user_super_gdtc_token_account: Box<Account<'info, TokenAccount>>, //上级的gdtc token账户
+    pub user_super_gdtc_token_account: Option<Box<Account<'info, TokenAccount>>>, //上级的gdtc token账户，根推荐人没有上级时不传
     #[account(mut)]
     pub user_gdtc_token_account: Account<'info, TokenAccount>, // 用户gdtc token账户
     #[account(mut)]
@@ -1,5 +1,5 @@
-use super::StakingInstance;
-use super::User;
+use super::{ReferralConfig, StakingInstance, StakingPool};
+use super::{Position, User};
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Token, TokenAccount, Transfer};
 
@@ -9,8 +9,27 @@ pub struct EnterStaking<'info> {
     pub authority: Signer<'info>,
     #[account(mut)]
     pub staking_instance: Account<'info, StakingInstance>,
+    #[account(
+        seeds = [crate::REFERRAL_CONFIG_SEED.as_ref(), staking_instance.key().as_ref()],
+        bump,
+    )]
+    pub referral_config: Account<'info, ReferralConfig>, // 推荐等级配置
+    #[account(mut)]
+    pub pool: Account<'info, StakingPool>,
     #[account(mut)]
     pub user_instance: Account<'info, User>,
+    #[account(
+        init,
+        seeds = [
+            crate::POSITION_SEED.as_ref(),
+            user_instance.key().as_ref(),
+            user_instance.position_count.to_le_bytes().as_ref()
+        ],
+        bump,
+        space = 8 + core::mem::size_of::<Position>(),
+        payer = authority
+    )]
+    pub position: Account<'info, Position>,
     #[account(mut)]
     pub user_lp_token_account: Account<'info, TokenAccount>,
     #[account(mut)]
@@ -0,0 +1,28 @@
+use super::StakingInstance;
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount, Transfer};
+
+#[derive(Accounts)]
+pub struct FundRewards<'info> {
+    pub funder: Signer<'info>, // 注入奖励的账户
+    #[account(mut)]
+    pub staking_instance: Account<'info, StakingInstance>,
+    #[account(mut)]
+    pub funder_token_account: Account<'info, TokenAccount>, // 注入方的 gdtc token账户
+    #[account(mut)]
+    pub gdtc_reward_out_account: Account<'info, TokenAccount>, //合约转出gdtc 的token账户
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> FundRewards<'info> {
+    pub fn into_transfer_to_reward_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.funder_token_account.to_account_info(),
+                to: self.gdtc_reward_out_account.to_account_info(),
+                authority: self.funder.to_account_info(),
+            },
+        )
+    }
+}
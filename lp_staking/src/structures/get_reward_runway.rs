@@ -0,0 +1,11 @@
+use super::StakingInstance;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct GetRewardRunway<'info> {
+    #[account(
+        seeds = [crate::STAKING_SEED.as_ref()],
+        bump,
+    )]
+    pub staking_instance: Account<'info, StakingInstance>,
+}
@@ -0,0 +1,19 @@
+use super::{ReferralConfig, StakingInstance};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct InitializeReferralConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub staking_instance: Account<'info, StakingInstance>,
+    #[account(
+        init,
+        seeds = [crate::REFERRAL_CONFIG_SEED.as_ref(), staking_instance.key().as_ref()],
+        bump,
+        space = 8 + core::mem::size_of::<ReferralConfig>(),
+        payer = authority
+    )]
+    pub referral_config: Account<'info, ReferralConfig>,
+    pub system_program: Program<'info, System>,
+}
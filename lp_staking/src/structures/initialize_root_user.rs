@@ -0,0 +1,23 @@
+use super::{StakingInstance, User};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct InitializeRootUser<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub staking_instance: Account<'info, StakingInstance>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + core::mem::size_of::<User>(),
+        seeds = [
+            crate::USER_SEED.as_ref(),
+            staking_instance.key().as_ref(),
+            authority.key().as_ref()
+        ],
+        bump,
+    )]
+    pub user_instance: Account<'info, User>, // 根推荐人的 User 账户
+    pub system_program: Program<'info, System>,
+}
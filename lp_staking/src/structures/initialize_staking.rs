@@ -1,4 +1,4 @@
-use super::{StakingInstance, StakingPool};
+use super::StakingInstance;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Mint, TokenAccount};
 
@@ -10,7 +10,7 @@ pub struct InitializeStaking<'info> {
         init, 
         seeds = [crate::STAKING_SEED.as_ref()],
         bump,
-        space = 8 + core::mem::size_of::<StakingInstance>()+3 * core::mem::size_of::<StakingPool>(),
+        space = 8 + core::mem::size_of::<StakingInstance>(),
         payer = authority
     )]
     pub staking_instance: Account<'info, StakingInstance>,
@@ -20,7 +20,7 @@ pub struct InitializeUser<'info> {
         bump,
     )]
     pub user_instance: Account<'info, User>,
-    
+    pub superior_instance: Box<Account<'info, User>>, // 推荐人的 User 账户，没有推荐人时传入根推荐人
     pub user_superior_token_account: Account<'info, TokenAccount>,
     pub system_program: Program<'info, System>,
 }
\ No newline at end of file
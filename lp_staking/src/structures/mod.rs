@@ -1,9 +1,17 @@
 use anchor_lang::prelude::*;
+pub mod add_pool;
 pub mod cancel_staking;
 pub mod claim_rewards;
 pub mod enter_staking;
+pub mod fund_rewards;
+pub mod get_reward_runway;
 pub mod initialize_staking;
+pub mod initialize_referral_config;
+pub mod initialize_root_user;
 pub mod initialize_user;
+pub mod set_pool_schedule;
+pub mod set_referral_config;
+pub mod set_reward_rate;
 
 // staking structures
 #[account]
@@ -11,17 +19,45 @@ pub struct StakingInstance {
     pub authority: Pubkey,          // 管理员账户
     pub reward_token_mint: Pubkey,  // 奖励代币 Mint 地址
     pub staking_token_mint: Pubkey, // 质押代币 Mint 地址
-    pub pools: [StakingPool; 3],    // 固定3个质押池
+    pub pool_count: u64,            // 已创建的质押池数量，也是下一个质押池的编号
     pub lp_token_account: Pubkey,   //合约接受lp的合约地址
+    pub total_emission_cap: u64,    // 所有质押池排放上限之和，不超过已注入的奖励
+    pub root_user: Pubkey,          // 协议根推荐人的 User 账户，没有推荐人的用户绑定到它
+    pub outstanding_rewards: u64,   // 奖励账户余额不足时欠付的奖励总额
+    pub total_funded: u64,          // 通过 fund_rewards 注入的奖励总量
+    pub total_emitted: u64,         // 已排放的奖励和已发放的额外推荐奖励总量，不超过 total_funded
+    pub referral_reserve_bps: u64,  // 每次排放为额外发放的推荐奖励预留的万分比，由推荐配置决定
+    pub referral_reserve: u64,      // 已为推荐奖励预留、尚未发放的数量
 }
 
-#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+// 每个质押池是独立的 PDA，seeds = [POOL_SEED, staking_instance, pool_id]
+#[account]
 pub struct StakingPool {
-    pub stake_type: u64, // 0 代表3个月，1 代表6个月，2代表12个月
+    pub staking_instance: Pubkey, // 所属的质押实例
+    pub pool_id: u64,             // 质押池编号
+    pub duration_months: u64,     // 锁仓月数
+    pub staking_open_from: u64,   // 开始接受质押的时间戳
+    pub staking_closes_at: u64,   // 停止接受质押的时间戳
+    pub rewards_end_at: u64,      // 停止产生奖励的时间戳
     pub reward_token_per_sec: u64, // 每秒奖励代币数量
     pub accumulated_reward_per_share: u64, // 累计奖励分摊
     pub last_reward_timestamp: u64, // 上次更新奖励的时间戳
     pub total_shares: u64, // 该池中质押的总份额
+    pub emission: EmissionSchedule, // 奖励排放计划
+    pub emission_cap: u64,   // 该池最多排放的奖励总量
+    pub total_emitted: u64,  // 该池已排放的奖励总量
+    pub pending_reward_rate: u64,       // 预定生效的新奖励速率
+    pub pending_rate_effective_at: u64, // 新奖励速率的生效时间戳，0 表示没有预定
+}
+
+// 奖励排放计划：从 emission_start 开始每 epoch_duration 秒为一个周期，
+// 第 k 个周期的速率为 reward_token_per_sec * (1 - decay_bps / 10000)^k，共 epoch_count 个周期
+#[derive(Debug, Clone, Copy, Default, AnchorSerialize, AnchorDeserialize)]
+pub struct EmissionSchedule {
+    pub emission_start: u64, // 第 0 个周期开始的时间戳
+    pub epoch_duration: u64, // 每个周期的秒数，为 0 表示速率不衰减
+    pub epoch_count: u64,    // 周期数量，之后不再排放
+    pub decay_bps: u64,      // 每个周期速率下降的万分比，5000 为减半
 }
 
 #[account]
@@ -29,20 +65,45 @@ pub struct User {
     //这个字段必须第一位
     pub total_deposited_amount: u64, // 用户总存入的质押金额
     pub user_superior_token_account: Pubkey, // 用户的上级 Token 账户
-    pub staked_info: [Staked; 10],   // 固定10个质押池
+    pub position_count: u64,         // 已创建的质押仓位数量，也是下一个仓位的 nonce
     pub isinit: bool,
     pub user_address: Pubkey,
+    pub superior: Pubkey,            // 绑定的推荐人（上级）的 User 账户，根推荐人为空
+    pub depth: u64,                  // 在推荐树中的深度，根推荐人为 0
+    pub team_volume: u64,            // N 层下级当前质押的团队业绩，解除质押时扣减
+    pub rank: u8,                    // 推荐等级，0 表示无等级，1-5 对应 L1-L5
+    pub period_volume: u64,          // 本评估周期内新增的团队业绩
+    pub last_rank_evaluation: u64,   // 本评估周期的开始时间戳
+    pub referral_reward_paid: u64,   // 作为上级累计获得的推荐奖励
+    pub referral_owed: u64,          // 作为更上层推荐人时因奖励账户余额不足欠付的等级奖励
+}
+
+// 推荐奖励和等级配置，seeds = [REFERRAL_CONFIG_SEED, staking_instance]
+#[account]
+pub struct ReferralConfig {
+    pub staking_instance: Pubkey,                     // 所属的质押实例
+    pub team_levels: u64,                             // 统计团队业绩的下级层数
+    pub rank_thresholds: [u64; crate::RANK_COUNT],    // 升级到 L1-L5 需要的本评估周期新增团队业绩
+    pub rank_reward_bps: [u64; crate::RANK_COUNT],    // L1-L5 从团队下级领取的奖励中获得的万分比，上层推荐人按级差获得
+    pub evaluation_period: u64,                       // 重新评估等级的周期（秒）
+    pub direct_reward_bps: u64,                       // 直接上级的推荐奖励万分比
+    pub qualification_threshold: u64,                 // 上级总质押超过该数量才能获得直推奖励
+    pub deduct_from_user: bool,                       // true 表示推荐奖励从用户奖励中扣除，false 表示额外发放
 }
 
-#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
-pub struct Staked {
-    pub deposited_amount: u64,   // 用户总存入的质押金额
-    pub reward_debt: u64,        // 用户奖励债务
-    pub accumulated_reward: u64, // 用户累计获得的奖励
-    pub is_staked: bool,         // 用户是否已质押
-    pub stake_type: u64,         // 质押类型
+// 每笔质押是独立的 PDA，seeds = [POSITION_SEED, user_instance, nonce]，解除质押且没有欠付奖励时关闭
+#[account]
+pub struct Position {
+    pub user_instance: Pubkey,   // 所属的用户账户
+    pub nonce: u64,              // 仓位编号，按用户单调递增
+    pub pool: Pubkey,            // 质押池账户
+    pub deposited_amount: u64,   // 存入的质押金额
+    pub reward_debt: u64,        // 奖励债务
+    pub accumulated_reward: u64, // 累计获得的奖励
     pub stake_start_time: u64,   // 质押开始时间（Unix 时间戳）
     pub stake_end_time: u64,     // 质押结束时间（Unix 时间戳）
-    pub receivedReward: u64,     //已领取收益
-    pub can_cancel_stake: bool,  //是否可以解除质押
-}
\ No newline at end of file
+    pub received_reward: u64,    // 已领取收益
+    pub can_cancel_stake: bool,  // 是否可以解除质押
+    pub reward_owed: u64,        // 奖励账户余额不足时欠用户的奖励
+    pub referral_owed: u64,      // 奖励账户余额不足时欠上级的推荐奖励
+}
@@ -0,0 +1,15 @@
+use super::{ReferralConfig, StakingInstance};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetReferralConfig<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub staking_instance: Account<'info, StakingInstance>,
+    #[account(
+        mut,
+        seeds = [crate::REFERRAL_CONFIG_SEED.as_ref(), staking_instance.key().as_ref()],
+        bump,
+    )]
+    pub referral_config: Account<'info, ReferralConfig>,
+}
@@ -0,0 +1,11 @@
+use super::{StakingInstance, StakingPool};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetRewardRate<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub staking_instance: Account<'info, StakingInstance>,
+    #[account(mut)]
+    pub pool: Account<'info, StakingPool>,
+}
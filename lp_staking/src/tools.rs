@@ -1,3 +1,6 @@
+use crate::constants::BPS_DENOMINATOR;
+use crate::structures::EmissionSchedule;
+
 pub const SECONDS_IN_MINUTE: u64 = 60;
 pub const SECONDS_IN_HOUR: u64 = 60 * SECONDS_IN_MINUTE;
 pub const SECONDS_IN_DAY: u64 = 24 * SECONDS_IN_HOUR;
@@ -90,18 +93,11 @@ fn date_to_timestamp(year: u64, month: u64, day: u64) -> u64 {
     timestamp
 }
 
-/// 根据质押类型，计算出时间戳，stake_type 为 0 代表三个月，1 代表六个月，2 代表十二个月，
-pub fn generate_release_timestamps(purchase_timestamp: u64, stake_type: u64) -> u64 {
+/// 根据质押池的锁仓月数，计算出到期时间戳
+pub fn generate_release_timestamps(purchase_timestamp: u64, months_to_add: u64) -> u64 {
     let (mut year, mut month, mut day) = timestamp_to_ymd(purchase_timestamp);
     let addtime = date_to_timestamp(year, month, day);
     let add = purchase_timestamp - addtime;
-    // 根据质押类型设置需要加的月份数
-    let months_to_add = match stake_type {
-        0 => 3,        // 三个月
-        1 => 6,        // 六个月
-        2 => 12,       // 十二个月
-        _ => return 0, // 无效的 stake_type，返回 0
-    };
 
     // 计算新日期
     for _ in 0..months_to_add {
@@ -135,4 +131,58 @@ pub fn test_generate_release_timestamp(purchase_timestamp: u64, stake_type: u64)
 
     // 增加秒数（转换为时间戳）
     purchase_timestamp + seconds_to_add
-}
\ No newline at end of file
+}
+
+/// 计算排放计划第 epoch 个周期的每秒排放量
+pub fn epoch_reward_rate(schedule: &EmissionSchedule, reward_token_per_sec: u64, epoch: u64) -> u64 {
+    if schedule.epoch_duration == 0 {
+        return reward_token_per_sec;
+    }
+    if epoch >= schedule.epoch_count {
+        return 0;
+    }
+    let keep_bps = BPS_DENOMINATOR.saturating_sub(schedule.decay_bps) as u128;
+    let mut rate = reward_token_per_sec as u128;
+    for _ in 0..epoch {
+        rate = rate * keep_bps / BPS_DENOMINATOR as u128;
+        if rate == 0 {
+            break;
+        }
+    }
+    rate as u64
+}
+
+/// 计算 [from, to) 时间段内按排放计划产生的奖励，跨越周期时分段按各周期的速率累加
+pub fn scheduled_emission(
+    schedule: &EmissionSchedule,
+    reward_token_per_sec: u64,
+    from: u64,
+    to: u64,
+) -> u128 {
+    if to <= from {
+        return 0;
+    }
+    if schedule.epoch_duration == 0 {
+        return reward_token_per_sec as u128 * (to - from) as u128;
+    }
+
+    let mut epoch = from.saturating_sub(schedule.emission_start) / schedule.epoch_duration;
+    let mut rate = epoch_reward_rate(schedule, reward_token_per_sec, epoch) as u128;
+    let keep_bps = BPS_DENOMINATOR.saturating_sub(schedule.decay_bps) as u128;
+
+    let mut total: u128 = 0;
+    let mut cursor = from;
+    while cursor < to && epoch < schedule.epoch_count && rate > 0 {
+        // 当前周期的结束时间，早于 emission_start 的部分按第 0 个周期计算
+        let epoch_end = schedule
+            .emission_start
+            .saturating_add(schedule.epoch_duration.saturating_mul(epoch + 1));
+        let segment_end = to.min(epoch_end);
+        total += rate * (segment_end - cursor) as u128;
+
+        cursor = segment_end;
+        epoch += 1;
+        rate = rate * keep_bps / BPS_DENOMINATOR as u128;
+    }
+    total
+}
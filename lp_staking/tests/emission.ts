@@ -0,0 +1,223 @@
+import { assert } from "chai";
+import { DEFAULT_REFERRAL, START, SECONDS_IN_YEAR, bn, setupStaking, tokenBalance, warpTo } from "./helpers";
+
+// 排放计划：模拟 20 年挖矿周期内的奖励排放
+
+const YEARS = 20;
+const SHARES = BigInt(1_000_000_000);
+/// 推荐奖励从用户奖励中扣除，不为推荐奖励预留注入，排放总量只受排放计划和上限限制
+const REFERRAL = { ...DEFAULT_REFERRAL, deductFromUser: true };
+
+/// 不规则的结算时间（相对 START 的秒数），覆盖周期边界前后和奖励结束之后
+const SETTLE_OFFSETS = [
+  7_777_777,
+  SECONDS_IN_YEAR,
+  4 * SECONDS_IN_YEAR - 1,
+  4 * SECONDS_IN_YEAR,
+  4 * SECONDS_IN_YEAR + 1,
+  7 * SECONDS_IN_YEAR + 12_345,
+  8 * SECONDS_IN_YEAR,
+  12 * SECONDS_IN_YEAR + 86_400,
+  16 * SECONDS_IN_YEAR - 3_600,
+  19 * SECONDS_IN_YEAR + 99_999,
+  YEARS * SECONDS_IN_YEAR,
+  (YEARS + 1) * SECONDS_IN_YEAR,
+  (YEARS + 2) * SECONDS_IN_YEAR,
+];
+
+/// 每 4 年减半，共 5 个周期覆盖 20 年
+function halvingPool(rewardPerSec: number, emissionCap: bigint) {
+  return {
+    durationMonths: 12 * (YEARS + 1),
+    rewardPerSec,
+    start: START,
+    end: START + YEARS * SECONDS_IN_YEAR,
+    rewardsEndAt: START + (YEARS + 1) * SECONDS_IN_YEAR,
+    epochDuration: 4 * SECONDS_IN_YEAR,
+    epochCount: YEARS / 4,
+    decayBps: 5_000,
+    emissionCap,
+  };
+}
+
+describe("emission", () => {
+  it("每 4 年减半，20 年共排放 1.9375 个周期的初始排放量", async () => {
+    const rate = 6_000_000;
+    // 1 + 1/2 + 1/4 + 1/8 + 1/16 = 1.9375
+    const expected = (BigInt(rate) * BigInt(4 * SECONDS_IN_YEAR) * BigInt(19_375)) / BigInt(10_000);
+
+    const staking = await setupStaking({ userCount: 1, referral: REFERRAL });
+    const { context, program, users } = staking;
+    await staking.fundRewards(expected);
+    const pool = await staking.addPool(halvingPool(rate, expected));
+    await staking.initializeUser(users[0], staking.root);
+    await staking.enterStaking(users[0], pool, SHARES);
+
+    for (const offset of SETTLE_OFFSETS) {
+      await warpTo(context, START + offset);
+      await staking.settle(pool);
+    }
+
+    const state = await program.account.stakingPool.fetch(pool);
+    const instance = await program.account.stakingInstance.fetch(staking.stakingInstance);
+    assert.ok(state.totalEmitted.eq(bn(expected)));
+    assert.ok(instance.totalEmitted.eq(state.totalEmitted));
+    assert.ok(state.lastRewardTimestamp.eq(state.rewardsEndAt));
+  });
+
+  it("一次结算跨越多个周期与分段结算结果相同", async () => {
+    const rate = 1_234_567;
+    const cap = BigInt(rate) * BigInt(YEARS * SECONDS_IN_YEAR);
+    const decayingPool = {
+      durationMonths: 12 * (YEARS + 1),
+      rewardPerSec: rate,
+      start: START,
+      end: START + YEARS * SECONDS_IN_YEAR,
+      rewardsEndAt: START + YEARS * SECONDS_IN_YEAR,
+      epochDuration: SECONDS_IN_YEAR,
+      epochCount: YEARS,
+      decayBps: 1_500,
+      emissionCap: cap,
+    };
+
+    const staking = await setupStaking({ userCount: 1, referral: REFERRAL });
+    const { context, program, users } = staking;
+    await staking.fundRewards(cap * BigInt(2));
+    const single = await staking.addPool(decayingPool);
+    const stepped = await staking.addPool(decayingPool);
+    await staking.initializeUser(users[0], staking.root);
+    await staking.enterStaking(users[0], single, SHARES);
+    await staking.enterStaking(users[0], stepped, SHARES);
+
+    for (const offset of SETTLE_OFFSETS) {
+      await warpTo(context, START + offset);
+      await staking.settle(stepped);
+    }
+    await staking.settle(single);
+
+    const singleState = await program.account.stakingPool.fetch(single);
+    const steppedState = await program.account.stakingPool.fetch(stepped);
+    assert.ok(singleState.totalEmitted.gt(bn(0)));
+    assert.ok(steppedState.totalEmitted.eq(singleState.totalEmitted));
+  });
+
+  it("累计排放不超过排放上限", async () => {
+    const rate = 6_000_000;
+    const cap = BigInt(123_456_789_012_345);
+
+    const staking = await setupStaking({ userCount: 1, referral: REFERRAL });
+    const { context, program, users } = staking;
+    await staking.fundRewards(cap);
+    const pool = await staking.addPool(halvingPool(rate, cap));
+    await staking.initializeUser(users[0], staking.root);
+    const position = await staking.enterStaking(users[0], pool, SHARES);
+
+    for (const offset of SETTLE_OFFSETS) {
+      await warpTo(context, START + offset);
+      await staking.settle(pool);
+    }
+    await staking.claimRewards(users[0], staking.root, pool, position);
+
+    const state = await program.account.stakingPool.fetch(pool);
+    assert.ok(state.totalEmitted.eq(bn(cap)));
+    assert.ok((await tokenBalance(context, users[0].rewardAccount)) <= cap);
+  });
+
+  it("预定速率从生效时间开始生效", async () => {
+    const staking = await setupStaking({ userCount: 1, referral: REFERRAL });
+    const { context, program, users } = staking;
+    await staking.fundRewards(BigInt(1_000_000));
+    const pool = await staking.addPool({
+      durationMonths: 12,
+      rewardPerSec: 100,
+      start: START,
+      end: START + SECONDS_IN_YEAR,
+      rewardsEndAt: START + 2 * SECONDS_IN_YEAR,
+      emissionCap: BigInt(1_000_000),
+    });
+    await staking.initializeUser(users[0], staking.root);
+    const position = await staking.enterStaking(users[0], pool, SHARES);
+
+    await program.methods
+      .setRewardRate(bn(40), bn(START + 1_000))
+      .accounts({ authority: staking.admin.publicKey, stakingInstance: staking.stakingInstance, pool })
+      .signers([staking.admin])
+      .rpc();
+
+    // 生效前仍按旧速率
+    await warpTo(context, START + 600);
+    await staking.claimRewards(users[0], staking.root, pool, position);
+    let state = await program.account.stakingPool.fetch(pool);
+    assert.ok(state.totalEmitted.eq(bn(60_000)));
+    assert.ok(state.rewardTokenPerSec.eq(bn(100)));
+
+    // 一次结算跨过生效时间：之前按旧速率，之后按新速率
+    await warpTo(context, START + 1_500);
+    await staking.claimRewards(users[0], staking.root, pool, position);
+    state = await program.account.stakingPool.fetch(pool);
+    assert.ok(state.totalEmitted.eq(bn(100_000 + 20_000)));
+    assert.ok(state.rewardTokenPerSec.eq(bn(40)));
+    assert.ok(state.pendingRateEffectiveAt.eq(bn(0)));
+  });
+
+  it("无人质押时预定速率生效，新速率不追溯到无人质押的时间段", async () => {
+    const staking = await setupStaking({ userCount: 1, referral: REFERRAL });
+    const { context, program, users } = staking;
+    await staking.fundRewards(BigInt(1_000_000));
+    const pool = await staking.addPool({
+      durationMonths: 12,
+      rewardPerSec: 100,
+      start: START,
+      end: START + SECONDS_IN_YEAR,
+      rewardsEndAt: START + 2 * SECONDS_IN_YEAR,
+      emissionCap: BigInt(1_000_000),
+    });
+    await program.methods
+      .setRewardRate(bn(40), bn(START + 1_000))
+      .accounts({ authority: staking.admin.publicKey, stakingInstance: staking.stakingInstance, pool })
+      .signers([staking.admin])
+      .rpc();
+
+    // 生效时间之后才有第一笔质押，质押时结算到当前时间并切换速率
+    await warpTo(context, START + 1_500);
+    await staking.initializeUser(users[0], staking.root);
+    const position = await staking.enterStaking(users[0], pool, SHARES);
+    let state = await program.account.stakingPool.fetch(pool);
+    assert.ok(state.rewardTokenPerSec.eq(bn(40)));
+    assert.ok(state.lastRewardTimestamp.eq(bn(START + 1_500)));
+    assert.ok(state.totalEmitted.eq(bn(0)));
+
+    // 只按新速率计算质押之后的 500 秒
+    await warpTo(context, START + 2_000);
+    await staking.claimRewards(users[0], staking.root, pool, position);
+    state = await program.account.stakingPool.fetch(pool);
+    assert.ok(state.totalEmitted.eq(bn(500 * 40)));
+  });
+
+  it("后加入的质押不分享加入之前产生的奖励", async () => {
+    const staking = await setupStaking({ userCount: 2, referral: REFERRAL });
+    const { context, users } = staking;
+    await staking.fundRewards(BigInt(1_000_000));
+    const pool = await staking.addPool({
+      durationMonths: 12,
+      rewardPerSec: 100,
+      start: START,
+      end: START + SECONDS_IN_YEAR,
+      rewardsEndAt: START + 2 * SECONDS_IN_YEAR,
+      emissionCap: BigInt(1_000_000),
+    });
+    await staking.initializeUser(users[0], staking.root);
+    await staking.initializeUser(users[1], staking.root);
+    const first = await staking.enterStaking(users[0], pool, SHARES);
+
+    await warpTo(context, START + 1_000);
+    const second = await staking.enterStaking(users[1], pool, SHARES);
+
+    // 前 1000 秒全部归第一个仓位，之后 1000 秒两个仓位平分
+    await warpTo(context, START + 2_000);
+    await staking.claimRewards(users[0], staking.root, pool, first);
+    await staking.claimRewards(users[1], staking.root, pool, second);
+    assert.equal(await tokenBalance(context, users[0].rewardAccount), BigInt(100_000 + 50_000));
+    assert.equal(await tokenBalance(context, users[1].rewardAccount), BigInt(50_000));
+  });
+});
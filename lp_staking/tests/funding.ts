@@ -0,0 +1,135 @@
+import { PublicKey } from "@solana/web3.js";
+import { assert } from "chai";
+import { DEFAULT_REFERRAL, START, SECONDS_IN_YEAR, bn, setupStaking, tokenBalance, warpTo } from "./helpers";
+
+// 奖励注入：推荐奖励计入排放、注入不足时削减推荐奖励、剩余注入可排放时间的估算
+
+const U64_MAX = bn(BigInt("18446744073709551615"));
+
+describe("funding", () => {
+  it("额外发放的推荐奖励由排放时的预留覆盖并计入 total_emitted，注入不足时用户照常领取、推荐奖励削减到可用的注入", async () => {
+    const staking = await setupStaking({ userCount: 2 });
+    const { context, program, admin, root, users, stakingInstance, referralConfig } = staking;
+    const [a, b] = users;
+    // 默认配置预留 10% 直推 + 5% 最高等级奖励
+    await staking.fundRewards(BigInt(115_000));
+    const pool = await staking.addPool({
+      durationMonths: 12,
+      rewardPerSec: 100,
+      start: START,
+      end: START + SECONDS_IN_YEAR,
+      rewardsEndAt: START + 2 * SECONDS_IN_YEAR,
+      emissionCap: BigInt(115_000),
+    });
+    await staking.initializeUser(a, root);
+    await staking.initializeUser(b, a);
+
+    // b 的质押让 a 升到 L1，获得 1% 的等级奖励
+    await warpTo(context, START + 10);
+    const position = await staking.enterStaking(b, pool, BigInt(1_000), [a.userInstance, root.userInstance]);
+
+    // 排放受注入限制：排放 100_000，同时预留 15_000
+    await warpTo(context, START + 5_010);
+    await staking.claimRewards(b, a, pool, position, [root]);
+    let instance = await program.account.stakingInstance.fetch(stakingInstance);
+    assert.equal(await tokenBalance(context, b.rewardAccount), BigInt(100_000));
+    assert.equal(await tokenBalance(context, a.rewardAccount), BigInt(1_000));
+    assert.ok(instance.totalEmitted.eq(bn(100_000 + 1_000)));
+    assert.ok(instance.referralReserve.eq(bn(0)));
+
+    // 剩余 14_000 按预留比例排放 12_173、预留 1_825
+    await warpTo(context, START + 10_000);
+    await staking.settle(pool);
+    instance = await program.account.stakingInstance.fetch(stakingInstance);
+    assert.ok(instance.totalEmitted.eq(bn(113_173)));
+    assert.ok(instance.referralReserve.eq(bn(1_825)));
+
+    // 排放之后调高等级奖励，预留和剩余注入都不足以发放推荐奖励
+    await program.methods
+      .setReferralConfig(
+        bn(DEFAULT_REFERRAL.teamLevels),
+        DEFAULT_REFERRAL.rankThresholds.map(bn),
+        [10_000, 200, 300, 400, 500].map(bn),
+        bn(DEFAULT_REFERRAL.evaluationPeriod),
+        bn(DEFAULT_REFERRAL.directRewardBps),
+        bn(DEFAULT_REFERRAL.qualificationThreshold),
+        DEFAULT_REFERRAL.deductFromUser
+      )
+      .accounts({ authority: admin.publicKey, stakingInstance, referralConfig })
+      .signers([admin])
+      .rpc();
+    // 用户照常领取 12_173，a 按 100% 应得 12_173，只能获得剩余的注入 1_827，削减的部分不再补发
+    await staking.claimRewards(b, a, pool, position, [root]);
+    instance = await program.account.stakingInstance.fetch(stakingInstance);
+    assert.equal(await tokenBalance(context, b.rewardAccount), BigInt(112_173));
+    assert.equal(await tokenBalance(context, a.rewardAccount), BigInt(1_000 + 1_827));
+    assert.ok(instance.totalEmitted.eq(instance.totalFunded));
+    assert.ok(instance.referralReserve.eq(bn(0)));
+
+    // 补充注入后质押池排放剩余的 2_827 到上限为止，推荐奖励重新足额发放
+    await staking.fundRewards(BigInt(100_000));
+    await warpTo(context, START + 10_100);
+    await staking.claimRewards(b, a, pool, position, [root]);
+    instance = await program.account.stakingInstance.fetch(stakingInstance);
+    assert.equal(await tokenBalance(context, b.rewardAccount), BigInt(115_000));
+    assert.equal(await tokenBalance(context, a.rewardAccount), BigInt(1_000 + 1_827 + 2_827));
+    assert.ok(instance.totalEmitted.eq(bn(115_000 + 2_827 + 2_827)));
+    assert.ok(instance.totalEmitted.add(instance.referralReserve).lte(instance.totalFunded));
+    assert.ok(instance.outstandingRewards.eq(bn(0)));
+  });
+
+  it("按排放计划、奖励结束时间、排放上限和推荐预留估算剩余注入可排放的秒数", async () => {
+    const staking = await setupStaking({ userCount: 1 });
+    const { context, program, root, users, stakingInstance } = staking;
+    await staking.fundRewards(BigInt(2_000_000));
+    const schedule = {
+      durationMonths: 12,
+      rewardPerSec: 100,
+      start: START,
+      end: START + SECONDS_IN_YEAR,
+      rewardsEndAt: START + 2 * SECONDS_IN_YEAR,
+    };
+    const pool = await staking.addPool({ ...schedule, emissionCap: BigInt(1_800_000) });
+    await staking.initializeUser(users[0], root);
+
+    const runway = (pools: PublicKey[]) =>
+      program.methods
+        .getRewardRunway()
+        .accounts({ stakingInstance })
+        .remainingAccounts(pools.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false })))
+        .view();
+
+    // 没有份额的质押池不排放
+    assert.ok((await runway([pool])).eq(U64_MAX));
+
+    // 每秒排放 100 并预留 15，2_000_000 可以排放 17_391 秒
+    await warpTo(context, START + 10);
+    await staking.enterStaking(users[0], pool, BigInt(1_000));
+    assert.ok((await runway([pool])).eq(bn(17_391)));
+    await warpTo(context, START + 4_010);
+    assert.ok((await runway([pool])).eq(bn(13_391)));
+
+    // endsEarly 在 990 秒后到达 rewards_end_at，只排放 99_000；capped 排放 500 秒后到达上限 50_000
+    const endsEarly = await staking.addPool({
+      ...schedule,
+      end: START + 5_000,
+      rewardsEndAt: START + 5_000,
+      emissionCap: BigInt(150_000),
+    });
+    const capped = await staking.addPool({ ...schedule, emissionCap: BigInt(50_000) });
+    await staking.enterStaking(users[0], endsEarly, BigInt(1_000));
+    await staking.enterStaking(users[0], capped, BigInt(1_000));
+    // 其余 2_000_000 - 149_000 * 1.15 可供原质押池排放 15_901 秒（从 START + 10 开始）
+    assert.ok((await runway([pool, endsEarly, capped])).eq(bn(15_901 - 4_000)));
+
+    // 必须按 pool_id 顺序传入全部质押池，且每个账户都是质押池 PDA
+    for (const pools of [[pool], [pool, capped, endsEarly], [pool, endsEarly, stakingInstance]]) {
+      try {
+        await runway(pools);
+        assert.fail("应该抛出错误");
+      } catch (error) {
+        assert.ok(error.toString().includes("InvalidPoolAccount"));
+      }
+    }
+  });
+});
@@ -0,0 +1,392 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { GdtcStaking, IDL } from "../target/types/gdtc_staking";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram } from "@solana/web3.js";
+import { ACCOUNT_SIZE, AccountLayout, MINT_SIZE, MintLayout, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { AddedAccount, Clock, ProgramTestContext, startAnchor } from "solana-bankrun";
+import { BankrunProvider } from "anchor-bankrun";
+
+// 不依赖本地验证器的测试共用：预置代币账户、初始化质押实例、调整链上时间
+
+export const PROGRAM_ID = new PublicKey("AAaENevvTe2HM7TcP6AHLq49NhZbB3xKPxYCrjKkyv2Q");
+export const START = 1_735_660_800;
+export const SECONDS_IN_DAY = 24 * 60 * 60;
+export const SECONDS_IN_YEAR = 365 * SECONDS_IN_DAY;
+
+const U64_MAX = BigInt("18446744073709551615");
+
+export function bn(value: number | bigint) {
+  return new anchor.BN(value.toString());
+}
+
+function pda(seeds: Buffer[]): PublicKey {
+  return PublicKey.findProgramAddressSync(seeds, PROGRAM_ID)[0];
+}
+
+export function userAddress(stakingInstance: PublicKey, authority: PublicKey): PublicKey {
+  return pda([Buffer.from("user_deposit"), stakingInstance.toBuffer(), authority.toBuffer()]);
+}
+
+export function poolAddress(stakingInstance: PublicKey, poolId: number): PublicKey {
+  return pda([Buffer.from("staking_pool"), stakingInstance.toBuffer(), bn(poolId).toArrayLike(Buffer, "le", 8)]);
+}
+
+export function positionAddress(userInstance: PublicKey, nonce: number): PublicKey {
+  return pda([Buffer.from("position"), userInstance.toBuffer(), bn(nonce).toArrayLike(Buffer, "le", 8)]);
+}
+
+function systemAccount(address: PublicKey): AddedAccount {
+  return {
+    address,
+    info: { lamports: 100 * LAMPORTS_PER_SOL, data: Buffer.alloc(0), owner: SystemProgram.programId, executable: false },
+  };
+}
+
+function mintAccount(address: PublicKey, mintAuthority: PublicKey): AddedAccount {
+  const data = Buffer.alloc(MINT_SIZE);
+  MintLayout.encode(
+    {
+      mintAuthorityOption: 1,
+      mintAuthority,
+      supply: U64_MAX,
+      decimals: 6,
+      isInitialized: true,
+      freezeAuthorityOption: 0,
+      freezeAuthority: PublicKey.default,
+    },
+    data
+  );
+  return { address, info: { lamports: LAMPORTS_PER_SOL, data, owner: TOKEN_PROGRAM_ID, executable: false } };
+}
+
+function tokenAccount(address: PublicKey, mint: PublicKey, owner: PublicKey, amount: bigint): AddedAccount {
+  const data = Buffer.alloc(ACCOUNT_SIZE);
+  AccountLayout.encode(
+    {
+      mint,
+      owner,
+      amount,
+      delegateOption: 0,
+      delegate: PublicKey.default,
+      state: 1,
+      isNativeOption: 0,
+      isNative: BigInt(0),
+      delegatedAmount: BigInt(0),
+      closeAuthorityOption: 0,
+      closeAuthority: PublicKey.default,
+    },
+    data
+  );
+  return { address, info: { lamports: LAMPORTS_PER_SOL, data, owner: TOKEN_PROGRAM_ID, executable: false } };
+}
+
+/// 测试用户：系统账户、持有 LP 的账户和接收奖励的账户
+export interface TestUser {
+  keypair: Keypair;
+  userInstance: PublicKey;
+  lpAccount: PublicKey;
+  rewardAccount: PublicKey;
+}
+
+/// 推荐等级配置，默认三层团队业绩、L1-L5 门槛和奖励比例，直推奖励 10%
+export interface ReferralSettings {
+  teamLevels: number;
+  rankThresholds: number[];
+  rankRewardBps: number[];
+  evaluationPeriod: number;
+  directRewardBps: number;
+  qualificationThreshold: number;
+  deductFromUser: boolean;
+}
+
+export const DEFAULT_REFERRAL: ReferralSettings = {
+  teamLevels: 3,
+  rankThresholds: [1_000, 5_000, 20_000, 50_000, 100_000],
+  rankRewardBps: [100, 200, 300, 400, 500],
+  evaluationPeriod: 30 * SECONDS_IN_DAY,
+  directRewardBps: 1_000,
+  qualificationThreshold: 2_000_000_000,
+  deductFromUser: false,
+};
+
+/// 启动程序并完成质押实例、推荐配置和根推荐人的初始化，userCount 个用户的代币账户预先创建
+export async function setupStaking(options: {
+  userCount: number;
+  referral?: ReferralSettings;
+}) {
+  const admin = Keypair.generate();
+  const rewardMint = Keypair.generate().publicKey;
+  const lpMint = Keypair.generate().publicKey;
+  const lpPda = pda([Buffer.from("lp_token")]);
+  const stakingInstance = pda([Buffer.from("staking_instance")]);
+  const referralConfig = pda([Buffer.from("referral_config"), stakingInstance.toBuffer()]);
+  const lpVault = Keypair.generate().publicKey;
+  const rewardVault = Keypair.generate().publicKey;
+  const adminRewardAccount = Keypair.generate().publicKey;
+  const adminLpAccount = Keypair.generate().publicKey;
+
+  const keypairs = Array.from({ length: options.userCount }, () => Keypair.generate());
+  const users: TestUser[] = keypairs.map((keypair) => ({
+    keypair,
+    userInstance: userAddress(stakingInstance, keypair.publicKey),
+    lpAccount: Keypair.generate().publicKey,
+    rewardAccount: Keypair.generate().publicKey,
+  }));
+
+  const accounts: AddedAccount[] = [
+    systemAccount(admin.publicKey),
+    mintAccount(rewardMint, admin.publicKey),
+    mintAccount(lpMint, admin.publicKey),
+    tokenAccount(lpVault, lpMint, lpPda, BigInt(0)),
+    tokenAccount(rewardVault, rewardMint, lpPda, BigInt(0)),
+    tokenAccount(adminRewardAccount, rewardMint, admin.publicKey, U64_MAX / BigInt(2)),
+    tokenAccount(adminLpAccount, lpMint, admin.publicKey, U64_MAX / BigInt(2)),
+    ...users.flatMap((user) => [
+      systemAccount(user.keypair.publicKey),
+      tokenAccount(user.lpAccount, lpMint, user.keypair.publicKey, U64_MAX / BigInt(2)),
+      tokenAccount(user.rewardAccount, rewardMint, user.keypair.publicKey, BigInt(0)),
+    ]),
+  ];
+
+  const context = await startAnchor(".", [], accounts);
+  const provider = new BankrunProvider(context);
+  const program = new Program<GdtcStaking>(IDL, PROGRAM_ID, provider as any);
+  await warpTo(context, START);
+
+  await program.methods
+    .initializeStaking()
+    .accounts({
+      authority: admin.publicKey,
+      stakingInstance,
+      rewardTokenMint: rewardMint,
+      stakingTokenMint: lpMint,
+      lpTokenAccount: lpVault,
+      systemProgram: SystemProgram.programId,
+    })
+    .signers([admin])
+    .rpc();
+
+  const referral = options.referral ?? DEFAULT_REFERRAL;
+  await program.methods
+    .initializeReferralConfig(
+      bn(referral.teamLevels),
+      referral.rankThresholds.map(bn),
+      referral.rankRewardBps.map(bn),
+      bn(referral.evaluationPeriod),
+      bn(referral.directRewardBps),
+      bn(referral.qualificationThreshold),
+      referral.deductFromUser
+    )
+    .accounts({ authority: admin.publicKey, stakingInstance, referralConfig, systemProgram: SystemProgram.programId })
+    .signers([admin])
+    .rpc();
+
+  const rootUser = userAddress(stakingInstance, admin.publicKey);
+  await program.methods
+    .initializeRootUser()
+    .accounts({
+      authority: admin.publicKey,
+      stakingInstance,
+      userInstance: rootUser,
+      systemProgram: SystemProgram.programId,
+    })
+    .signers([admin])
+    .rpc();
+
+  const root: TestUser = {
+    keypair: admin,
+    userInstance: rootUser,
+    lpAccount: adminLpAccount,
+    rewardAccount: adminRewardAccount,
+  };
+
+  /// 注入奖励
+  async function fundRewards(amount: bigint) {
+    await program.methods
+      .fundRewards(bn(amount))
+      .accounts({
+        funder: admin.publicKey,
+        stakingInstance,
+        funderTokenAccount: adminRewardAccount,
+        gdtcRewardOutAccount: rewardVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+  }
+
+  /// 新增质押池，返回质押池地址
+  async function addPool(pool: {
+    durationMonths: number;
+    rewardPerSec: number;
+    start: number;
+    end: number;
+    rewardsEndAt: number;
+    epochDuration?: number;
+    epochCount?: number;
+    decayBps?: number;
+    emissionCap: bigint;
+  }): Promise<PublicKey> {
+    const instance = await program.account.stakingInstance.fetch(stakingInstance);
+    const address = poolAddress(stakingInstance, instance.poolCount.toNumber());
+    await program.methods
+      .addPool(
+        bn(pool.durationMonths),
+        bn(pool.rewardPerSec),
+        bn(pool.start),
+        bn(pool.end),
+        bn(pool.rewardsEndAt),
+        bn(pool.epochDuration ?? 0),
+        bn(pool.epochCount ?? 0),
+        bn(pool.decayBps ?? 0),
+        bn(pool.emissionCap)
+      )
+      .accounts({ authority: admin.publicKey, stakingInstance, pool: address, systemProgram: SystemProgram.programId })
+      .signers([admin])
+      .rpc();
+    return address;
+  }
+
+  /// 通过推荐人绑定上级，创建用户的 User 账户
+  async function initializeUser(user: TestUser, superior: TestUser) {
+    await program.methods
+      .initializeUser()
+      .accounts({
+        authority: user.keypair.publicKey,
+        stakingInstance,
+        userInstance: user.userInstance,
+        superiorInstance: superior.userInstance,
+        userSuperiorTokenAccount: superior.rewardAccount,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user.keypair])
+      .rpc();
+  }
+
+  /// 质押 LP，ancestors 为上级、上上级……的 User 账户，返回新仓位地址
+  async function enterStaking(user: TestUser, pool: PublicKey, amount: bigint, ancestors: PublicKey[] = []) {
+    const { positionCount } = await program.account.user.fetch(user.userInstance);
+    const position = positionAddress(user.userInstance, positionCount.toNumber());
+    await program.methods
+      .enterStaking(bn(amount))
+      .accounts({
+        authority: user.keypair.publicKey,
+        stakingInstance,
+        referralConfig,
+        pool,
+        userInstance: user.userInstance,
+        position,
+        userLpTokenAccount: user.lpAccount,
+        gdtcLpInAccount: lpVault,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(ancestors.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })))
+      .signers([user.keypair])
+      .rpc();
+    return position;
+  }
+
+  /// 领取仓位奖励，superior 为用户的直接上级，根推荐人没有上级时传入 null；
+  /// ancestors 为上上级、再上一级……，按级差发放等级奖励时依次传入 User 账户和接收奖励的 Token 账户
+  async function claimRewards(
+    user: TestUser,
+    superior: TestUser | null,
+    pool: PublicKey,
+    position: PublicKey,
+    ancestors: TestUser[] = []
+  ) {
+    await program.methods
+      .claimRewards()
+      .accounts({
+        authority: user.keypair.publicKey,
+        stakingInstance,
+        referralConfig,
+        pool,
+        userInstance: user.userInstance,
+        position,
+        superInstance: superior?.userInstance ?? null,
+        userSuperGdtcTokenAccount: superior?.rewardAccount ?? null,
+        userGdtcTokenAccount: user.rewardAccount,
+        gdtcRewardOutAccount: rewardVault,
+        pdaAccount: lpPda,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        ancestors.flatMap((ancestor) => [
+          { pubkey: ancestor.userInstance, isSigner: false, isWritable: true },
+          { pubkey: ancestor.rewardAccount, isSigner: false, isWritable: true },
+        ])
+      )
+      .signers([user.keypair])
+      .rpc();
+  }
+
+  /// 解除到期的质押，ancestors 与 enterStaking 相同
+  async function cancelStaking(user: TestUser, pool: PublicKey, position: PublicKey, ancestors: PublicKey[] = []) {
+    await program.methods
+      .cancelStaking()
+      .accounts({
+        authority: user.keypair.publicKey,
+        stakingInstance,
+        referralConfig,
+        pool,
+        userInstance: user.userInstance,
+        position,
+        userLpTokenAccount: user.lpAccount,
+        gdtcLpInAccount: lpVault,
+        pdaAccount: lpPda,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(ancestors.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })))
+      .signers([user.keypair])
+      .rpc();
+  }
+
+  /// 按当前速率调用 set_reward_rate，把质押池结算到当前时间
+  async function settle(pool: PublicKey) {
+    const { rewardTokenPerSec } = await program.account.stakingPool.fetch(pool);
+    await program.methods
+      .setRewardRate(rewardTokenPerSec, bn(0))
+      .accounts({ authority: admin.publicKey, stakingInstance, pool })
+      .signers([admin])
+      .rpc();
+  }
+
+  return {
+    context,
+    program,
+    admin,
+    root,
+    users,
+    stakingInstance,
+    referralConfig,
+    rewardVault,
+    fundRewards,
+    addPool,
+    initializeUser,
+    enterStaking,
+    claimRewards,
+    cancelStaking,
+    settle,
+  };
+}
+
+/// 读取代币账户余额
+export async function tokenBalance(context: ProgramTestContext, address: PublicKey): Promise<bigint> {
+  const account = await context.banksClient.getAccount(address);
+  return AccountLayout.decode(account.data).amount;
+}
+
+/// 把链上时间调整到 unixTimestamp
+export async function warpTo(context: ProgramTestContext, unixTimestamp: number) {
+  // 同时推进 slot，避免相同交易因 blockhash 不变被当作重复交易
+  const clock = await context.banksClient.getClock();
+  const slot = clock.slot + BigInt(1);
+  context.warpToSlot(slot);
+  context.setClock(
+    new Clock(slot, clock.epochStartTimestamp, clock.epoch, clock.leaderScheduleEpoch, BigInt(unixTimestamp))
+  );
+}
@@ -0,0 +1,120 @@
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { START, SECONDS_IN_YEAR, bn, poolAddress, setupStaking, warpTo } from "./helpers";
+
+// 质押池注册表：每个质押池是按 pool_id 派生的独立 PDA，由管理员新增
+
+const SCHEDULE = {
+  durationMonths: 12,
+  rewardPerSec: 100,
+  start: START,
+  end: START + SECONDS_IN_YEAR,
+  rewardsEndAt: START + 2 * SECONDS_IN_YEAR,
+};
+
+describe("pools", () => {
+  it("新增质押池按 pool_id 创建独立的 PDA 并记录配置，各池的份额互不影响", async () => {
+    const staking = await setupStaking({ userCount: 1 });
+    const { context, program, root, users, stakingInstance } = staking;
+    await staking.fundRewards(BigInt(3_000_000));
+
+    const first = await staking.addPool({ ...SCHEDULE, emissionCap: BigInt(1_000_000) });
+    const second = await staking.addPool({
+      ...SCHEDULE,
+      durationMonths: 24,
+      rewardPerSec: 250,
+      end: START + 2 * SECONDS_IN_YEAR,
+      rewardsEndAt: START + 3 * SECONDS_IN_YEAR,
+      epochDuration: SECONDS_IN_YEAR,
+      epochCount: 3,
+      decayBps: 5_000,
+      emissionCap: BigInt(2_000_000),
+    });
+    assert.ok(first.equals(poolAddress(stakingInstance, 0)));
+    assert.ok(second.equals(poolAddress(stakingInstance, 1)));
+
+    const instance = await program.account.stakingInstance.fetch(stakingInstance);
+    assert.ok(instance.poolCount.eq(bn(2)));
+    assert.ok(instance.totalEmissionCap.eq(bn(3_000_000)));
+
+    const state = await program.account.stakingPool.fetch(second);
+    assert.ok(state.stakingInstance.equals(stakingInstance));
+    assert.ok(state.poolId.eq(bn(1)));
+    assert.ok(state.durationMonths.eq(bn(24)));
+    assert.ok(state.rewardTokenPerSec.eq(bn(250)));
+    assert.ok(state.stakingOpenFrom.eq(bn(START)));
+    assert.ok(state.stakingClosesAt.eq(bn(START + 2 * SECONDS_IN_YEAR)));
+    assert.ok(state.rewardsEndAt.eq(bn(START + 3 * SECONDS_IN_YEAR)));
+    assert.ok(state.emission.epochDuration.eq(bn(SECONDS_IN_YEAR)));
+    assert.ok(state.emission.epochCount.eq(bn(3)));
+    assert.ok(state.emission.decayBps.eq(bn(5_000)));
+    assert.ok(state.emissionCap.eq(bn(2_000_000)));
+    assert.ok(state.totalShares.eq(bn(0)));
+    assert.ok(state.totalEmitted.eq(bn(0)));
+
+    await staking.initializeUser(users[0], root);
+    await warpTo(context, START + 10);
+    await staking.enterStaking(users[0], second, BigInt(1_000));
+    assert.ok((await program.account.stakingPool.fetch(first)).totalShares.eq(bn(0)));
+    assert.ok((await program.account.stakingPool.fetch(second)).totalShares.eq(bn(1_000)));
+  });
+
+  it("非管理员、无效配置或排放上限超过注入时拒绝新增质押池", async () => {
+    const staking = await setupStaking({ userCount: 1 });
+    const { program, admin, users, stakingInstance } = staking;
+    await staking.fundRewards(BigInt(1_000_000));
+
+    const addPool = (
+      authority: typeof admin,
+      pool: typeof SCHEDULE & { emissionCap: bigint; epochDuration?: number; epochCount?: number },
+      address: PublicKey = poolAddress(stakingInstance, 0)
+    ) =>
+      program.methods
+        .addPool(
+          bn(pool.durationMonths),
+          bn(pool.rewardPerSec),
+          bn(pool.start),
+          bn(pool.end),
+          bn(pool.rewardsEndAt),
+          bn(pool.epochDuration ?? 0),
+          bn(pool.epochCount ?? 0),
+          bn(0),
+          bn(pool.emissionCap)
+        )
+        .accounts({ authority: authority.publicKey, stakingInstance, pool: address, systemProgram: SystemProgram.programId })
+        .signers([authority])
+        .rpc();
+
+    const cases: [typeof admin, Parameters<typeof addPool>[1], string][] = [
+      [users[0].keypair, { ...SCHEDULE, emissionCap: BigInt(1_000) }, "NotAuthority"],
+      [admin, { ...SCHEDULE, durationMonths: 0, emissionCap: BigInt(1_000) }, "InvalidPoolConfig"],
+      [admin, { ...SCHEDULE, end: START, emissionCap: BigInt(1_000) }, "InvalidPoolConfig"],
+      [admin, { ...SCHEDULE, rewardsEndAt: SCHEDULE.end - 1, emissionCap: BigInt(1_000) }, "InvalidPoolConfig"],
+      [admin, { ...SCHEDULE, epochDuration: SECONDS_IN_YEAR, epochCount: 0, emissionCap: BigInt(1_000) }, "InvalidPoolConfig"],
+      [admin, { ...SCHEDULE, emissionCap: BigInt(1_000_001) }, "EmissionCapExceedsFunding"],
+    ];
+    for (const [authority, pool, expected] of cases) {
+      try {
+        await addPool(authority, pool);
+        assert.fail("应该抛出错误");
+      } catch (error) {
+        assert.ok(error.toString().includes(expected));
+      }
+    }
+
+    // 地址必须是下一个 pool_id 对应的 PDA
+    try {
+      await addPool(admin, { ...SCHEDULE, emissionCap: BigInt(1_000) }, poolAddress(stakingInstance, 1));
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("ConstraintSeeds"));
+    }
+
+    // 以上失败都没有改变注册表，之后仍从 pool_id 0 开始，上限之和可以等于注入总量
+    await addPool(admin, { ...SCHEDULE, emissionCap: BigInt(600_000) });
+    await staking.addPool({ ...SCHEDULE, emissionCap: BigInt(400_000) });
+    const instance = await program.account.stakingInstance.fetch(stakingInstance);
+    assert.ok(instance.poolCount.eq(bn(2)));
+    assert.ok(instance.totalEmissionCap.eq(bn(1_000_000)));
+  });
+});
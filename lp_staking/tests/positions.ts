@@ -0,0 +1,149 @@
+import { assert } from "chai";
+import { START, SECONDS_IN_DAY, SECONDS_IN_YEAR, bn, positionAddress, setupStaking, tokenBalance, warpTo } from "./helpers";
+
+// 质押仓位：每次质押创建独立的 Position PDA，解除质押后关闭
+
+const POSITIONS = 12;
+
+function monthlyPool(emissionCap: bigint) {
+  return {
+    durationMonths: 1,
+    rewardPerSec: 100,
+    start: START,
+    end: START + SECONDS_IN_YEAR,
+    rewardsEndAt: START + 2 * SECONDS_IN_YEAR,
+    emissionCap,
+  };
+}
+
+describe("positions", () => {
+  it("每次质押按递增的 nonce 创建仓位，数量不受旧的 10 个上限限制，解除质押后关闭仓位并退还 LP", async () => {
+    const staking = await setupStaking({ userCount: 1 });
+    const { context, program, root, users } = staking;
+    const [user] = users;
+    await staking.fundRewards(BigInt(1_000_000_000));
+    const pool = await staking.addPool(monthlyPool(BigInt(1_000_000_000)));
+    await staking.initializeUser(user, root);
+
+    await warpTo(context, START + 10);
+    const lpBefore = await tokenBalance(context, user.lpAccount);
+    const positions = [];
+    for (let nonce = 0; nonce < POSITIONS; nonce++) {
+      const position = await staking.enterStaking(user, pool, BigInt(1_000 + nonce), [root.userInstance]);
+      assert.ok(position.equals(positionAddress(user.userInstance, nonce)));
+      const state = await program.account.position.fetch(position);
+      assert.ok(state.userInstance.equals(user.userInstance));
+      assert.ok(state.pool.equals(pool));
+      assert.ok(state.nonce.eq(bn(nonce)));
+      assert.ok(state.depositedAmount.eq(bn(1_000 + nonce)));
+      positions.push(position);
+    }
+    const deposited = BigInt(POSITIONS * 1_000 + (POSITIONS * (POSITIONS - 1)) / 2);
+    let account = await program.account.user.fetch(user.userInstance);
+    assert.ok(account.positionCount.eq(bn(POSITIONS)));
+    assert.ok(account.totalDepositedAmount.eq(bn(deposited)));
+    assert.equal(await tokenBalance(context, user.lpAccount), lpBefore - deposited);
+
+    // 到期后先领取奖励，再逐个解除质押，仓位账户被关闭
+    await warpTo(context, START + 40 * SECONDS_IN_DAY);
+    for (const position of positions) {
+      await staking.claimRewards(user, root, pool, position);
+      await staking.cancelStaking(user, pool, position, [root.userInstance]);
+      assert.isNull(await context.banksClient.getAccount(position));
+    }
+    assert.equal(await tokenBalance(context, user.lpAccount), lpBefore);
+    assert.ok((await program.account.stakingPool.fetch(pool)).totalShares.eq(bn(0)));
+
+    // 关闭的仓位不会被重新使用，下一次质押的 nonce 继续递增
+    const next = await staking.enterStaking(user, pool, BigInt(500), [root.userInstance]);
+    assert.ok(next.equals(positionAddress(user.userInstance, POSITIONS)));
+    account = await program.account.user.fetch(user.userInstance);
+    assert.ok(account.positionCount.eq(bn(POSITIONS + 1)));
+    assert.ok(account.totalDepositedAmount.eq(bn(500)));
+  });
+
+  it("到期后解除质押之前继续产生的奖励仍可领取，解除质押时结算的奖励领取后才关闭仓位", async () => {
+    const staking = await setupStaking({ userCount: 1 });
+    const { context, program, root, users } = staking;
+    const [user] = users;
+    await staking.fundRewards(BigInt(1_000_000_000));
+    const pool = await staking.addPool(monthlyPool(BigInt(1_000_000_000)));
+    await staking.initializeUser(user, root);
+
+    await warpTo(context, START + 10);
+    const position = await staking.enterStaking(user, pool, BigInt(1_000), [root.userInstance]);
+
+    // 到期后第一次领取标记可以解除质押
+    const maturedAt = START + 40 * SECONDS_IN_DAY;
+    await warpTo(context, maturedAt);
+    await staking.claimRewards(user, root, pool, position);
+    const claimed = await tokenBalance(context, user.rewardAccount);
+    assert.equal(claimed, BigInt(100 * (40 * SECONDS_IN_DAY - 10)));
+    assert.isTrue((await program.account.position.fetch(position)).canCancelStake);
+
+    // 同一时间再次领取没有新奖励
+    await warpTo(context, maturedAt);
+    try {
+      await staking.claimRewards(user, root, pool, position);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("NoRewardsToClaim"));
+    }
+
+    // 独占质押池，到期后的 1000 秒仍然产生奖励并可以领取
+    await warpTo(context, maturedAt + 1_000);
+    await staking.claimRewards(user, root, pool, position);
+    assert.equal(await tokenBalance(context, user.rewardAccount), claimed + BigInt(100_000));
+
+    // 上次领取之后的 500 秒奖励在解除质押时结算，仓位保留到领取之后
+    await warpTo(context, maturedAt + 1_500);
+    await staking.cancelStaking(user, pool, position, [root.userInstance]);
+    const state = await program.account.position.fetch(position);
+    assert.ok(state.depositedAmount.eq(bn(0)));
+    assert.ok(state.accumulatedReward.eq(bn(50_000)));
+
+    // 之后不再产生奖励，领取结算的奖励后再次解除质押关闭仓位
+    await warpTo(context, maturedAt + 2_000);
+    await staking.claimRewards(user, root, pool, position);
+    assert.equal(await tokenBalance(context, user.rewardAccount), claimed + BigInt(150_000));
+    await staking.cancelStaking(user, pool, position, [root.userInstance]);
+    assert.isNull(await context.banksClient.getAccount(position));
+  });
+
+  it("未到期、未领取奖励、其他用户的仓位或质押池不一致时拒绝解除质押", async () => {
+    const staking = await setupStaking({ userCount: 2 });
+    const { context, program, root, users } = staking;
+    const [a, b] = users;
+    await staking.fundRewards(BigInt(2_000_000_000));
+    const pool = await staking.addPool(monthlyPool(BigInt(1_000_000_000)));
+    const other = await staking.addPool(monthlyPool(BigInt(1_000_000_000)));
+    await staking.initializeUser(a, root);
+    await staking.initializeUser(b, root);
+
+    await warpTo(context, START + 10);
+    const position = await staking.enterStaking(a, pool, BigInt(1_000), [root.userInstance]);
+    await staking.enterStaking(b, pool, BigInt(1_000), [root.userInstance]);
+
+    const expectError = async (action: Promise<unknown>, expected: string) => {
+      try {
+        await action;
+        assert.fail("应该抛出错误");
+      } catch (error) {
+        assert.ok(error.toString().includes(expected));
+      }
+    };
+
+    await expectError(staking.cancelStaking(a, pool, position, [root.userInstance]), "StakingNotMatured");
+
+    await warpTo(context, START + 40 * SECONDS_IN_DAY);
+    await expectError(staking.cancelStaking(a, pool, position, [root.userInstance]), "NeedCliamRewards");
+    await expectError(staking.cancelStaking(b, pool, position, [root.userInstance]), "NoStakingToCancel");
+    await expectError(staking.cancelStaking(a, other, position, [root.userInstance]), "InvalidStakeType");
+
+    // 以上失败都没有改变仓位，之后仍可以正常解除质押
+    await staking.claimRewards(a, root, pool, position);
+    await staking.cancelStaking(a, pool, position, [root.userInstance]);
+    assert.isNull(await context.banksClient.getAccount(position));
+    assert.ok((await program.account.stakingPool.fetch(pool)).totalShares.eq(bn(1_000)));
+  });
+});
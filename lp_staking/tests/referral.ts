@@ -0,0 +1,292 @@
+import { PublicKey } from "@solana/web3.js";
+import { assert } from "chai";
+import {
+  DEFAULT_REFERRAL,
+  TestUser,
+  START,
+  SECONDS_IN_DAY,
+  SECONDS_IN_YEAR,
+  bn,
+  setupStaking,
+  tokenBalance,
+  warpTo,
+} from "./helpers";
+
+// 推荐等级：本周期新增业绩升级和保级、解除质押扣减团队业绩、等级奖励沿推荐链按级差发放；
+// 推荐关系：根推荐人没有上级，领取奖励时校验绑定的上级
+
+const PERIOD = DEFAULT_REFERRAL.evaluationPeriod;
+const FUNDING = BigInt(1_000_000_000_000);
+
+/// 一年期、每秒 100 奖励的质押池
+function yearlyPool(durationMonths = 12) {
+  return {
+    durationMonths,
+    rewardPerSec: 100,
+    start: START,
+    end: START + SECONDS_IN_YEAR,
+    rewardsEndAt: START + 2 * SECONDS_IN_YEAR,
+    emissionCap: FUNDING,
+  };
+}
+
+describe("referral", () => {
+  it("本周期新增业绩达到门槛时立即升级", async () => {
+    const staking = await setupStaking({ userCount: 2 });
+    const { context, program, root, users } = staking;
+    const [a, b] = users;
+    await staking.fundRewards(FUNDING);
+    const pool = await staking.addPool(yearlyPool());
+    await staking.initializeUser(a, root);
+    await staking.initializeUser(b, a);
+
+    await warpTo(context, START + 10);
+    await staking.enterStaking(b, pool, BigInt(999), [a.userInstance, root.userInstance]);
+    assert.equal((await program.account.user.fetch(a.userInstance)).rank, 0);
+
+    await staking.enterStaking(b, pool, BigInt(3_001), [a.userInstance, root.userInstance]);
+    assert.equal((await program.account.user.fetch(a.userInstance)).rank, 1);
+
+    await staking.enterStaking(b, pool, BigInt(16_000), [a.userInstance, root.userInstance]);
+    const state = await program.account.user.fetch(a.userInstance);
+    assert.equal(state.rank, 3);
+    assert.ok(state.teamVolume.eq(bn(20_000)));
+    assert.ok(state.periodVolume.eq(bn(20_000)));
+  });
+
+  it("每个周期的新增业绩达到门槛才能保级，缺少业绩的周期逐个降级", async () => {
+    const staking = await setupStaking({ userCount: 2 });
+    const { context, program, root, users } = staking;
+    const [a, b] = users;
+    await staking.fundRewards(FUNDING);
+    const pool = await staking.addPool(yearlyPool());
+    await staking.initializeUser(a, root);
+    await staking.initializeUser(b, a);
+    const ancestors = [a.userInstance, root.userInstance];
+
+    await warpTo(context, START + 10);
+    const position = await staking.enterStaking(b, pool, BigInt(20_000), ancestors);
+    assert.equal((await program.account.user.fetch(a.userInstance)).rank, 3);
+
+    // 第 1 个周期新增业绩达到 L3 门槛，保级；新周期只新增 1 不会提高等级
+    await warpTo(context, START + PERIOD + 10);
+    await staking.enterStaking(b, pool, BigInt(1), ancestors);
+    let state = await program.account.user.fetch(a.userInstance);
+    assert.equal(state.rank, 3);
+    assert.ok(state.periodVolume.eq(bn(1)));
+    assert.ok(state.lastRankEvaluation.eq(bn(START + PERIOD)));
+
+    // 第 2 个周期只新增 1，低于当前等级的门槛，降一级；评估时间晚于周期结束也不会让周期漂移
+    await warpTo(context, START + 2 * PERIOD + 12_345);
+    await staking.claimRewards(b, a, pool, position, [root]);
+    state = await program.account.user.fetch(a.userInstance);
+    assert.equal(state.rank, 2);
+    assert.ok(state.lastRankEvaluation.eq(bn(START + 2 * PERIOD)));
+
+    // 降级后少量新增业绩不能恢复等级
+    await staking.enterStaking(b, pool, BigInt(1), ancestors);
+    assert.equal((await program.account.user.fetch(a.userInstance)).rank, 2);
+
+    // 第 3 个周期业绩不足降一级，之后两个完整周期没有业绩各降一级
+    await warpTo(context, START + 5 * PERIOD + 10);
+    await staking.claimRewards(b, a, pool, position, [root]);
+    state = await program.account.user.fetch(a.userInstance);
+    assert.equal(state.rank, 0);
+    assert.ok(state.lastRankEvaluation.eq(bn(START + 5 * PERIOD)));
+    assert.ok(state.teamVolume.eq(bn(20_002)));
+
+    // 一个周期内重新达到门槛后恢复等级
+    await staking.enterStaking(b, pool, BigInt(5_000), ancestors);
+    assert.equal((await program.account.user.fetch(a.userInstance)).rank, 2);
+  });
+
+  it("解除质押时沿推荐关系扣减团队业绩", async () => {
+    const staking = await setupStaking({ userCount: 3 });
+    const { context, program, root, users } = staking;
+    const [a, b, c] = users;
+    await staking.fundRewards(FUNDING);
+    const pool = await staking.addPool(yearlyPool(1));
+    await staking.initializeUser(a, root);
+    await staking.initializeUser(b, a);
+    await staking.initializeUser(c, b);
+    const ancestors = [b.userInstance, a.userInstance, root.userInstance];
+
+    await warpTo(context, START + 10);
+    const position = await staking.enterStaking(c, pool, BigInt(6_000), ancestors);
+    for (const user of [b, a, root]) {
+      assert.ok((await program.account.user.fetch(user.userInstance)).teamVolume.eq(bn(6_000)));
+    }
+
+    // 到期后先领取奖励标记可以解除质押，再解除质押
+    await warpTo(context, START + 40 * SECONDS_IN_DAY);
+    await staking.claimRewards(c, b, pool, position, [a, root]);
+    await staking.cancelStaking(c, pool, position, ancestors);
+    for (const user of [b, a, root]) {
+      const state = await program.account.user.fetch(user.userInstance);
+      assert.ok(state.teamVolume.eq(bn(0)));
+      assert.ok(state.periodVolume.eq(bn(0)));
+    }
+  });
+
+  it("等级奖励沿推荐链按级差发放，上层推荐人只获得高出下层比例的部分", async () => {
+    const staking = await setupStaking({ userCount: 4 });
+    const { context, root, users } = staking;
+    const [a, b, c, d] = users;
+    await staking.fundRewards(FUNDING);
+    const pool = await staking.addPool(yearlyPool());
+    // a 是 b 和 d 的上级，b 是 c 的上级
+    await staking.initializeUser(a, root);
+    await staking.initializeUser(b, a);
+    await staking.initializeUser(c, b);
+    await staking.initializeUser(d, a);
+
+    // b 为 L1；a 和根推荐人的新增业绩都是 20_000，均为 L3
+    await warpTo(context, START + 10);
+    const fromC = await staking.enterStaking(c, pool, BigInt(1_000), [b.userInstance, a.userInstance, root.userInstance]);
+    const fromD = await staking.enterStaking(d, pool, BigInt(19_000), [a.userInstance, root.userInstance]);
+    await warpTo(context, START + 1_000);
+
+    // 上上级必须依次传入
+    try {
+      await staking.claimRewards(c, b, pool, fromC);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("InvalidReferralAccount"));
+    }
+    try {
+      await staking.claimRewards(c, b, pool, fromC, [root, a]);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("InvalidReferralAccount"));
+    }
+
+    // c 的直接上级 b 是 L1 获得 1%，a 是 L3 获得高出的 2%，根推荐人与 a 同为 L3 不再获得
+    const rootBefore = await tokenBalance(context, root.rewardAccount);
+    await staking.claimRewards(c, b, pool, fromC, [a, root]);
+    const rewardC = await tokenBalance(context, c.rewardAccount);
+    assert.ok(rewardC > BigInt(0));
+    assert.equal(await tokenBalance(context, b.rewardAccount), (rewardC * BigInt(100)) / BigInt(10_000));
+    assert.equal(await tokenBalance(context, a.rewardAccount), (rewardC * BigInt(200)) / BigInt(10_000));
+    assert.equal(await tokenBalance(context, root.rewardAccount), rootBefore);
+
+    // d 的直接上级 a 是 L3，获得 3%
+    await staking.claimRewards(d, a, pool, fromD, [root]);
+    const rewardD = await tokenBalance(context, d.rewardAccount);
+    assert.equal(
+      await tokenBalance(context, a.rewardAccount),
+      (rewardC * BigInt(200)) / BigInt(10_000) + (rewardD * BigInt(300)) / BigInt(10_000)
+    );
+    assert.equal(await tokenBalance(context, root.rewardAccount), rootBefore);
+  });
+
+  it("上级总质押超过门槛获得直推奖励，扣除模式下从用户奖励中扣除", async () => {
+    const staking = await setupStaking({
+      userCount: 2,
+      referral: { ...DEFAULT_REFERRAL, qualificationThreshold: 1_000, deductFromUser: true },
+    });
+    const { context, program, root, users } = staking;
+    const [a, b] = users;
+    await staking.fundRewards(FUNDING);
+    const pool = await staking.addPool(yearlyPool());
+    await staking.initializeUser(a, root);
+    await staking.initializeUser(b, a);
+
+    await warpTo(context, START + 10);
+    await staking.enterStaking(a, pool, BigInt(1_001), [root.userInstance]);
+    const position = await staking.enterStaking(b, pool, BigInt(500), [a.userInstance, root.userInstance]);
+
+    await warpTo(context, START + 1_000);
+    const rootBefore = await tokenBalance(context, root.rewardAccount);
+    await staking.claimRewards(b, a, pool, position, [root]);
+    const userReward = await tokenBalance(context, b.rewardAccount);
+    const superiorReward = await tokenBalance(context, a.rewardAccount);
+    const rootReward = (await tokenBalance(context, root.rewardAccount)) - rootBefore;
+    // a 没有等级只获得直推奖励，根推荐人是 L1，按级差获得 1%，两者都从用户奖励中扣除
+    const total = userReward + superiorReward + rootReward;
+    assert.equal(superiorReward, (total * BigInt(1_000)) / BigInt(10_000));
+    assert.equal(rootReward, (total * BigInt(100)) / BigInt(10_000));
+    assert.ok((await program.account.user.fetch(a.userInstance)).referralRewardPaid.eq(bn(superiorReward)));
+  });
+
+  it("根推荐人没有上级，领取奖励时不传上级账户，奖励全部归根推荐人", async () => {
+    const staking = await setupStaking({ userCount: 0 });
+    const { context, program, root } = staking;
+    await staking.fundRewards(FUNDING);
+    const pool = await staking.addPool(yearlyPool());
+
+    const state = await program.account.user.fetch(root.userInstance);
+    assert.ok(state.superior.equals(PublicKey.default));
+    assert.ok(state.userSuperiorTokenAccount.equals(PublicKey.default));
+
+    await warpTo(context, START + 10);
+    const position = await staking.enterStaking(root, pool, BigInt(1_000_000));
+    await warpTo(context, START + 1_010);
+    const before = await tokenBalance(context, root.rewardAccount);
+    await staking.claimRewards(root, null, pool, position);
+
+    // 独占质押池，1000 秒的奖励全部归根推荐人
+    assert.equal((await tokenBalance(context, root.rewardAccount)) - before, BigInt(100_000));
+    assert.ok((await program.account.user.fetch(root.userInstance)).referralRewardPaid.eq(bn(0)));
+  });
+
+  it("领取奖励时上级账户必须是绑定的上级，不能是用户自己", async () => {
+    const staking = await setupStaking({ userCount: 2 });
+    const { context, root, users } = staking;
+    const [a, b] = users;
+    await staking.fundRewards(FUNDING);
+    const pool = await staking.addPool(yearlyPool());
+    await staking.initializeUser(a, root);
+    await staking.initializeUser(b, root);
+
+    await warpTo(context, START + 10);
+    const position = await staking.enterStaking(a, pool, BigInt(1_000), [root.userInstance]);
+    await warpTo(context, START + 1_000);
+
+    const cases: [TestUser | null, string][] = [
+      [a, "SelfReferral"],
+      [b, "InvalidReferralAccount"],
+      [null, "InvalidReferralAccount"],
+    ];
+    for (const [superior, expected] of cases) {
+      try {
+        await staking.claimRewards(a, superior, pool, position);
+        assert.fail("应该抛出错误");
+      } catch (error) {
+        assert.ok(error.toString().includes(expected));
+      }
+    }
+  });
+
+  it("拒绝无效的推荐配置", async () => {
+    const staking = await setupStaking({ userCount: 0 });
+    const { program, admin, stakingInstance, referralConfig } = staking;
+    const invalid = [
+      { ...DEFAULT_REFERRAL, teamLevels: 0 },
+      { ...DEFAULT_REFERRAL, teamLevels: 11 },
+      { ...DEFAULT_REFERRAL, rankThresholds: [1, 2, 2, 3, 4] },
+      { ...DEFAULT_REFERRAL, rankRewardBps: [10_001, 0, 0, 0, 0] },
+      { ...DEFAULT_REFERRAL, evaluationPeriod: 0 },
+      { ...DEFAULT_REFERRAL, directRewardBps: 10_001 },
+    ];
+    for (const referral of invalid) {
+      try {
+        await program.methods
+          .setReferralConfig(
+            bn(referral.teamLevels),
+            referral.rankThresholds.map(bn),
+            referral.rankRewardBps.map(bn),
+            bn(referral.evaluationPeriod),
+            bn(referral.directRewardBps),
+            bn(referral.qualificationThreshold),
+            referral.deductFromUser
+          )
+          .accounts({ authority: admin.publicKey, stakingInstance, referralConfig })
+          .signers([admin])
+          .rpc();
+        assert.fail("应该抛出错误");
+      } catch (error) {
+        assert.ok(error.toString().includes("InvalidReferralConfig"));
+      }
+    }
+  });
+});
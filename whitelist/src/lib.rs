@@ -1,5 +1,8 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use anchor_spl::token::spl_token::instruction::AuthorityType;
 
 declare_id!("6iadRi4ps7itomsTNa34RikS6hkmx2z5Ls1h9EqLPu1y");
 
@@ -18,6 +21,33 @@ pub const RELEASE_PERIOD: u8 = 36;
 /// 时间常量
 pub const SECONDS_PER_MONTH: i64 = 30 * 24 * 60 * 60;
 
+/// 白名单地址数量
+pub const BENEFICIARY_COUNT: usize = 3;
+
+/// 管理员恢复受益人地址的时间锁（7天）
+pub const ROTATION_TIMELOCK: i64 = 7 * 24 * 60 * 60;
+
+/// 提现地址变更生效的延迟（3天）
+pub const WITHDRAWAL_ADDRESS_DELAY: i64 = 3 * 24 * 60 * 60;
+
+/// 每个受益人最多的释放分期数量
+pub const MAX_TRANCHES: usize = 8;
+
+/// 释放记录账户保存的最近记录数量，超出后覆盖最早的记录
+pub const RELEASE_HISTORY_CAPACITY: usize = 64;
+
+/// 归属池最多的受益人数量
+pub const MAX_BENEFICIARIES: usize = 10;
+
+/// 归属池名称的最大字节数
+pub const MAX_POOL_NAME_LEN: usize = 32;
+
+/// 白名单状态账户的当前布局版本
+pub const STATE_VERSION: u8 = 2;
+
+/// 白名单状态账户的预留字节数，后续版本新增字段时使用
+pub const STATE_RESERVED_LEN: usize = 64;
+
 /// BioNeo 白名单合约
 /// 负责白名单代币的释放管理
 /// 
@@ -25,17 +55,32 @@ pub const SECONDS_PER_MONTH: i64 = 30 * 24 * 60 * 60;
 /// 1. 支持三个等级的白名单地址
 /// 2. 分36个月逐步释放代币
 /// 3. 每个白名单地址有固定的释放比例
+/// 4. 受益人可以更换地址，丢失私钥时由管理员经时间锁恢复
+/// 5. 可撤销的受益人被撤销后，未释放代币转回回收账户
+/// 6. 任何人都可以代受益人释放代币，代币只转入受益人的关联代币账户
+/// 7. 每个受益人支持多个释放分期（TGE 解锁、锁定期、线性释放）
+/// 8. 分配可以铸造为可转让的持仓凭证（NFT），由凭证持有人释放代币
+/// 9. 锁仓代币可以委托给质押或投票账户，投票权重等于尚未释放的数量
+/// 10. 每次释放写入链上释放记录（环形缓冲），可由客户端读取完整历史
+/// 11. 任何管理员都可以创建独立的归属池（团队、顾问、合作方等），白名单是其中一个实例
+/// 12. 状态账户带布局版本，旧版本账户通过 migrate_state 原地升级
 #[program]
 pub mod whitelist {
     use super::*;
 
     /// 初始化白名单
     /// 
+    /// 参数：
+    /// - revocable: 白名单1、2、3是否可撤销，初始化后不可修改
+    /// 
     /// 功能：
-    /// 1. 计算每个白名单的释放金额
+    /// 1. 计算每个白名单的释放金额，三份之和等于总代币数量
     /// 2. 设置开始释放时间为当前时间
+    /// 3. 记录撤销后未释放代币的回收账户
+    /// 4. 创建释放记录账户
     pub fn initialize_whitelist(
         ctx: Context<InitializeWhitelist>,
+        revocable: [bool; BENEFICIARY_COUNT],
     ) -> Result<()> {
         let whitelist_state = &mut ctx.accounts.whitelist_state;
         
@@ -45,26 +90,31 @@ pub mod whitelist {
             WhitelistError::AlreadyInitialized
         );
 
+        // 验证回收账户与白名单代币为同一种代币
+        require!(
+            ctx.accounts.treasury_token_account.mint == ctx.accounts.token_account.mint,
+            WhitelistError::InvalidTreasury
+        );
+
         whitelist_state.authority = ctx.accounts.authority.key();
-        whitelist_state.whitelist1 = WHITELIST_ADDRESS_1;
-        whitelist_state.whitelist2 = WHITELIST_ADDRESS_2;
-        whitelist_state.whitelist3 = WHITELIST_ADDRESS_3;
+        whitelist_state.treasury = ctx.accounts.treasury_token_account.key();
+        whitelist_state.bump = ctx.bumps.whitelist_state;
         whitelist_state.total_amount = ctx.accounts.token_account.amount;
         whitelist_state.start_time = Clock::get()?.unix_timestamp;
+        whitelist_state.version = STATE_VERSION;
+
+        // 计算每个白名单的释放金额，取整余数归白名单3
+        let [whitelist1_amount, whitelist2_amount, whitelist3_amount] =
+            split_whitelist_amount(whitelist_state.total_amount)?;
 
-        // 计算每个白名单的释放金额
-        whitelist_state.whitelist1_amount = whitelist_state.total_amount
-            .checked_mul(5)
-            .and_then(|v| v.checked_div(10))
-            .ok_or(WhitelistError::ArithmeticOverflow)?; // 2.5%
-        whitelist_state.whitelist2_amount = whitelist_state.total_amount
-            .checked_mul(3)
-            .and_then(|v| v.checked_div(10))
-            .ok_or(WhitelistError::ArithmeticOverflow)?; // 1.5%
-        whitelist_state.whitelist3_amount = whitelist_state.total_amount
-            .checked_mul(2)
-            .and_then(|v| v.checked_div(10))
-            .ok_or(WhitelistError::ArithmeticOverflow)?; // 1.0%
+        whitelist_state.beneficiaries = vec![
+            Beneficiary::new(WHITELIST_ADDRESS_1, whitelist1_amount, revocable[0], whitelist_state.start_time),
+            Beneficiary::new(WHITELIST_ADDRESS_2, whitelist2_amount, revocable[1], whitelist_state.start_time),
+            Beneficiary::new(WHITELIST_ADDRESS_3, whitelist3_amount, revocable[2], whitelist_state.start_time),
+        ];
+
+        // 初始化释放记录账户
+        ctx.accounts.release_history.init(whitelist_state.key(), BENEFICIARY_COUNT);
 
         emit!(WhitelistInitialized {
             authority: whitelist_state.authority,
@@ -75,6 +125,93 @@ pub mod whitelist {
         Ok(())
     }
 
+    /// 创建归属池
+    /// 
+    /// 参数：
+    /// - pool_id: 归属池编号，与管理员地址一起作为状态账户的种子
+    /// - name: 归属池名称，例如 "team"、"advisors"
+    /// - start_time: 开始释放时间
+    /// - allocations: 受益人分配和释放分期
+    /// 
+    /// 功能：
+    /// 1. 任何地址都可以作为管理员创建归属池，创建后与白名单使用相同的释放、撤销、暂停等指令
+    /// 2. 归属池代币账户由 ["vesting_vault", 归属池状态账户] 推导；已结束的归属池编号不能重新创建
+    /// 3. 所有分配的代币在创建时从管理员代币账户转入
+    pub fn create_vesting_pool(
+        ctx: Context<CreateVestingPool>,
+        pool_id: u64,
+        name: String,
+        start_time: i64,
+        allocations: Vec<PoolAllocation>,
+    ) -> Result<()> {
+        require!(name.len() <= MAX_POOL_NAME_LEN, WhitelistError::PoolNameTooLong);
+        require!(
+            !allocations.is_empty() && allocations.len() <= MAX_BENEFICIARIES,
+            WhitelistError::InvalidBeneficiaryCount
+        );
+        require!(
+            ctx.accounts.treasury_token_account.mint == ctx.accounts.mint.key(),
+            WhitelistError::InvalidTreasury
+        );
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.authority = ctx.accounts.authority.key();
+        pool_state.treasury = ctx.accounts.treasury_token_account.key();
+        pool_state.bump = ctx.bumps.pool_state;
+        pool_state.is_pool = true;
+        pool_state.pool_id = pool_id;
+        pool_state.name = name;
+        pool_state.version = STATE_VERSION;
+        pool_state.start_time = start_time;
+
+        let mut total_amount: u64 = 0;
+        for allocation in allocations.iter() {
+            pool_state.validate_new_beneficiary(&allocation.address)?;
+
+            let mut beneficiary = Beneficiary::new(
+                allocation.address,
+                allocation.amount,
+                allocation.revocable,
+                start_time,
+            );
+            if !allocation.tranches.is_empty() {
+                beneficiary.set_tranches(&allocation.tranches)?;
+            }
+            pool_state.beneficiaries.push(beneficiary);
+
+            total_amount = total_amount
+                .checked_add(allocation.amount)
+                .ok_or(WhitelistError::ArithmeticOverflow)?;
+        }
+        pool_state.total_amount = total_amount;
+
+        // 从管理员账户转入分配的代币
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.authority_token_account.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            total_amount,
+        )?;
+
+        ctx.accounts.release_history.init(pool_state.key(), allocations.len());
+
+        emit!(VestingPoolCreated {
+            pool: pool_state.key(),
+            authority: pool_state.authority,
+            pool_id,
+            name: pool_state.name.clone(),
+            total_amount,
+            beneficiary_count: allocations.len() as u8,
+        });
+
+        Ok(())
+    }
+
     /// 释放代币
     /// 
     /// 功能：
@@ -84,262 +221,2396 @@ pub mod whitelist {
     /// 
     /// 安全控制：
     /// 1. 时间控制：只能在指定时间后释放
-    /// 2. 权限控制：只有白名单地址可以释放
+    /// 2. 权限控制：只有白名单地址可以释放，已铸造持仓凭证的分配由凭证持有人释放
     /// 3. 数量控制：最多释放36个月
     /// 4. 溢出保护：使用 checked_add 防止溢出
     pub fn release_tokens(ctx: Context<ReleaseTokens>) -> Result<()> {
         let whitelist_state = &mut ctx.accounts.whitelist_state;
         let current_time = Clock::get()?.unix_timestamp;
 
-        // 验证调用者是否为白名单地址
-        let user_address = ctx.accounts.user.key();
         require!(
-            user_address == whitelist_state.whitelist1 ||
-            user_address == whitelist_state.whitelist2 ||
-            user_address == whitelist_state.whitelist3,
-            WhitelistError::UnauthorizedRelease
+            ctx.accounts.release_history.whitelist_state == whitelist_state.key(),
+            WhitelistError::InvalidReleaseHistory
         );
 
+        // 验证调用者是否为白名单地址，或持有该分配的持仓凭证
+        let user_address = ctx.accounts.user.key();
+        let index = whitelist_state
+            .signer_index(&user_address, ctx.accounts.position_token_account.as_ref())?;
+
         // 验证代币账户所有者
         require!(
             ctx.accounts.token_account.owner == whitelist_state.key(),
             WhitelistError::InvalidTokenAccount
         );
 
+        // 验证接收账户：同一种代币，且为受益人的 ATA 或已生效的提现地址
+        // 持仓凭证可以转让，原持有人登记的提现地址对凭证分配无效
+        let user_token_account = &ctx.accounts.user_token_account;
+        require!(
+            user_token_account.mint == ctx.accounts.token_account.mint,
+            WhitelistError::InvalidTokenAccount
+        );
+        let associated_token_account =
+            get_associated_token_address(&user_address, &ctx.accounts.token_account.mint);
+        let beneficiary = &whitelist_state.beneficiaries[index];
+        require!(
+            user_token_account.key() == associated_token_account
+                || (!beneficiary.is_tokenized()
+                    && beneficiary.is_withdrawal_address(&user_token_account.key(), current_time)),
+            WhitelistError::InvalidDestination
+        );
+
         // 验证释放时间
         require!(
             current_time >= whitelist_state.start_time,
             WhitelistError::ReleaseTimeNotReached
         );
 
-        // 计算已经过去的月份
-        let months_passed = ((current_time - whitelist_state.start_time) / SECONDS_PER_MONTH) as u64;
-        require!(months_passed >= 1, WhitelistError::NoMoreReleases);
+        // 暂停中的受益人不能领取
+        require!(
+            whitelist_state.beneficiaries[index].paused_at == 0,
+            WhitelistError::BeneficiaryPaused
+        );
+
+        // 计算并转移本次可领取金额
+        let current_claim = release_vested(
+            whitelist_state,
+            &mut ctx.accounts.release_history,
+            index,
+            &ctx.accounts.token_account,
+            ctx.accounts.user_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            current_time,
+        )?;
+
+        require!(current_claim > 0, WhitelistError::NoMoreReleases);
+
+        Ok(())
+    }
+
+    /// 设置提现地址
+    /// 
+    /// 功能：
+    /// 1. 受益人登记一个非 ATA 的接收账户（冷钱包、交易所充值地址等）
+    /// 2. 新地址需等待 WITHDRAWAL_ADDRESS_DELAY 后才能用于领取
+    /// 3. 传入默认地址时立即清除提现地址和未生效的变更
+    pub fn set_withdrawal_address(
+        ctx: Context<SetWithdrawalAddress>,
+        withdrawal_address: Pubkey,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        let current_time = Clock::get()?.unix_timestamp;
 
-        // 获取用户的释放金额和已领取金额
-        let (release_amount, claimed_amount) = if user_address == whitelist_state.whitelist1 {
-            (whitelist_state.whitelist1_amount, &mut whitelist_state.whitelist1_claimed)
-        } else if user_address == whitelist_state.whitelist2 {
-            (whitelist_state.whitelist2_amount, &mut whitelist_state.whitelist2_claimed)
+        let beneficiary_address = ctx.accounts.beneficiary.key();
+        let index = whitelist_state
+            .beneficiary_index(&beneficiary_address)
+            .ok_or(WhitelistError::UnauthorizedRelease)?;
+        let beneficiary = &mut whitelist_state.beneficiaries[index];
+        require!(!beneficiary.is_tokenized(), WhitelistError::PositionTokenized);
+
+        let effective_time = if withdrawal_address == Pubkey::default() {
+            beneficiary.withdrawal_address = Pubkey::default();
+            beneficiary.pending_withdrawal_address = Pubkey::default();
+            beneficiary.withdrawal_effective_time = 0;
+            current_time
         } else {
-            (whitelist_state.whitelist3_amount, &mut whitelist_state.whitelist3_claimed)
+            let effective_time = current_time
+                .checked_add(WITHDRAWAL_ADDRESS_DELAY)
+                .ok_or(WhitelistError::ArithmeticOverflow)?;
+            // 已经到期的变更先生效，再登记新的变更
+            beneficiary.apply_pending_withdrawal_address(current_time);
+            beneficiary.pending_withdrawal_address = withdrawal_address;
+            beneficiary.withdrawal_effective_time = effective_time;
+            effective_time
         };
 
-        // 计算月度释放金额
-        let monthly_release = release_amount
-            .checked_div(RELEASE_PERIOD as u64)
-            .ok_or(WhitelistError::ArithmeticOverflow)?;
+        emit!(WithdrawalAddressUpdated {
+            beneficiary: beneficiary_address,
+            withdrawal_address,
+            effective_time,
+        });
 
-        // 计算应领取的总金额
-        let total_should_claim = monthly_release
-            .checked_mul(months_passed)
-            .ok_or(WhitelistError::ArithmeticOverflow)?;
+        Ok(())
+    }
 
-        // 计算本次可领取金额
-        let current_claim = total_should_claim
-            .checked_sub(*claimed_amount)
-            .ok_or(WhitelistError::ArithmeticOverflow)?;
+    /// 代任意白名单地址释放代币
+    /// 
+    /// 功能：
+    /// 1. 任何人都可以调用，无需受益人签名
+    /// 2. 代币只能转入受益人的关联代币账户（ATA），地址在链上推导并校验
+    /// 3. 已铸造持仓凭证的分配需传入凭证代币账户，代币转入凭证持有人的 ATA
+    pub fn crank_release(
+        ctx: Context<CrankRelease>,
+        beneficiary: Pubkey,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        let current_time = Clock::get()?.unix_timestamp;
 
-        require!(current_claim > 0, WhitelistError::NoMoreReleases);
+        let index = whitelist_state
+            .beneficiary_index(&beneficiary)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
 
-        // 转移代币
-        let transfer_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            token::Transfer {
-                from: ctx.accounts.token_account.to_account_info(),
-                to: ctx.accounts.user_token_account.to_account_info(),
-                authority: whitelist_state.to_account_info(),
-                token_program: ctx.accounts.token_program.to_account_info(),
-            },
+        require!(
+            ctx.accounts.token_account.owner == whitelist_state.key(),
+            WhitelistError::InvalidTokenAccount
+        );
+        require!(
+            ctx.accounts.release_history.whitelist_state == whitelist_state.key(),
+            WhitelistError::InvalidReleaseHistory
+        );
+        let recipient = match &ctx.accounts.position_token_account {
+            Some(position_token_account) => {
+                whitelist_state.verify_position_holder(index, position_token_account)?;
+                position_token_account.owner
+            }
+            None => {
+                require!(
+                    !whitelist_state.beneficiaries[index].is_tokenized(),
+                    WhitelistError::PositionTokenized
+                );
+                beneficiary
+            }
+        };
+        require!(
+            ctx.accounts.beneficiary_token_account.key()
+                == get_associated_token_address(&recipient, &ctx.accounts.token_account.mint),
+            WhitelistError::InvalidBeneficiaryTokenAccount
+        );
+        require!(
+            current_time >= whitelist_state.start_time,
+            WhitelistError::ReleaseTimeNotReached
+        );
+        require!(
+            whitelist_state.beneficiaries[index].paused_at == 0,
+            WhitelistError::BeneficiaryPaused
         );
 
-        token::transfer(transfer_ctx, current_claim)?;
-
-        // 更新状态
-        *claimed_amount = total_should_claim;
-        whitelist_state.last_release_time = current_time;
-        whitelist_state.total_released = whitelist_state.total_released
-            .checked_add(current_claim)
-            .ok_or(WhitelistError::ArithmeticOverflow)?;
+        let current_claim = release_vested(
+            whitelist_state,
+            &mut ctx.accounts.release_history,
+            index,
+            &ctx.accounts.token_account,
+            ctx.accounts.beneficiary_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            current_time,
+        )?;
 
-        emit!(TokensReleased {
-            user: user_address,
-            amount: current_claim,
-            total_claimed: *claimed_amount,
-        });
+        require!(current_claim > 0, WhitelistError::NoMoreReleases);
 
         Ok(())
     }
 
-    /// 查询可领取代币数量
+    /// 一次为所有白名单地址释放代币
     /// 
     /// 功能：
-    /// 1. 验证地址是否为白名单地址
-    /// 2. 计算当前可领取的代币数量
-    #[view]
-    pub fn get_claimable_amount(
-        ctx: Context<GetClaimableAmount>,
-    ) -> Result<u64> {
-        let whitelist_state = &ctx.accounts.whitelist_state;
+    /// 1. 任何人都可以调用
+    /// 2. remaining_accounts 依次传入每个受益人的关联代币账户（白名单为白名单1、2、3）
+    /// 3. 没有可领取代币、暂停中或已铸造持仓凭证的地址直接跳过，不会导致整笔交易失败
+    ///    （持仓凭证分配请使用 crank_release）
+    pub fn crank_release_all<'info>(
+        ctx: Context<'_, '_, '_, 'info, CrankReleaseAll<'info>>,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
         let current_time = Clock::get()?.unix_timestamp;
 
-        // 验证查询地址是否为白名单地址
-        let user_address = ctx.accounts.user.key();
         require!(
-            user_address == whitelist_state.whitelist1 ||
-            user_address == whitelist_state.whitelist2 ||
-            user_address == whitelist_state.whitelist3,
-            WhitelistError::UnauthorizedRelease
+            ctx.accounts.token_account.owner == whitelist_state.key(),
+            WhitelistError::InvalidTokenAccount
+        );
+        require!(
+            ctx.accounts.release_history.whitelist_state == whitelist_state.key(),
+            WhitelistError::InvalidReleaseHistory
+        );
+        require!(
+            ctx.remaining_accounts.len() == whitelist_state.beneficiaries.len(),
+            WhitelistError::InvalidBeneficiaryTokenAccount
         );
-
-        // 验证释放时间
         require!(
             current_time >= whitelist_state.start_time,
             WhitelistError::ReleaseTimeNotReached
         );
 
-        // 计算已经过去的月份
-        let months_passed = ((current_time - whitelist_state.start_time) / SECONDS_PER_MONTH) as u64;
-        require!(months_passed >= 1, WhitelistError::NoMoreReleases);
+        let mint = ctx.accounts.token_account.mint;
+        let extend_on_pause = whitelist_state.extend_schedule_on_pause;
+        for (index, destination) in ctx.remaining_accounts.iter().enumerate() {
+            // 先跳过本次不会转账的地址，这些地址的代币账户可以尚未创建
+            let beneficiary = &whitelist_state.beneficiaries[index];
+            if beneficiary.paused_at != 0
+                || beneficiary.is_tokenized()
+                || beneficiary.claimable_amount(current_time, extend_on_pause)? == 0
+            {
+                continue;
+            }
 
-        // 获取用户的释放金额和已领取金额
-        let (release_amount, claimed_amount) = if user_address == whitelist_state.whitelist1 {
-            (whitelist_state.whitelist1_amount, whitelist_state.whitelist1_claimed)
-        } else if user_address == whitelist_state.whitelist2 {
-            (whitelist_state.whitelist2_amount, whitelist_state.whitelist2_claimed)
-        } else {
-            (whitelist_state.whitelist3_amount, whitelist_state.whitelist3_claimed)
-        };
+            require!(
+                destination.key() == get_associated_token_address(&beneficiary.address, &mint),
+                WhitelistError::InvalidBeneficiaryTokenAccount
+            );
+            // 确认是有效的代币账户
+            Account::<TokenAccount>::try_from(destination)?;
 
-        // 计算月度释放金额
-        let monthly_release = release_amount
-            .checked_div(RELEASE_PERIOD as u64)
-            .ok_or(WhitelistError::ArithmeticOverflow)?;
+            release_vested(
+                whitelist_state,
+                &mut ctx.accounts.release_history,
+                index,
+                &ctx.accounts.token_account,
+                destination.clone(),
+                &ctx.accounts.token_program,
+                current_time,
+            )?;
+        }
 
-        // 计算应领取的总金额
-        let total_should_claim = monthly_release
-            .checked_mul(months_passed)
-            .ok_or(WhitelistError::ArithmeticOverflow)?;
+        Ok(())
+    }
 
-        // 计算本次可领取金额
-        let current_claim = total_should_claim
-            .checked_sub(claimed_amount)
-            .ok_or(WhitelistError::ArithmeticOverflow)?;
+    /// 设置受益人的释放分期
+    /// 
+    /// 参数：
+    /// - address: 受益人地址
+    /// - tranches: 按开始时间排序的分期列表，如 TGE 解锁、锁定期和线性释放
+    /// 
+    /// 安全控制：
+    /// 1. 权限控制：只有管理员可以设置
+    /// 2. 分期总额必须等于受益人的分配数量，不能增减分配
+    /// 3. 受益人尚未有任何代币释放时才能设置，已撤销的受益人不能设置
+    pub fn set_beneficiary_tranches(
+        ctx: Context<SetBeneficiaryTranches>,
+        address: Pubkey,
+        tranches: Vec<Tranche>,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        let current_time = Clock::get()?.unix_timestamp;
+        let extend_on_pause = whitelist_state.extend_schedule_on_pause;
 
-        require!(current_claim > 0, WhitelistError::NoMoreReleases);
+        require!(
+            ctx.accounts.authority.key() == whitelist_state.authority,
+            WhitelistError::NotAuthority
+        );
+
+        let index = whitelist_state
+            .beneficiary_index(&address)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
+        let beneficiary = &mut whitelist_state.beneficiaries[index];
+        require!(beneficiary.revoked_at == 0, WhitelistError::AlreadyRevoked);
+        require!(
+            beneficiary.claimed == 0
+                && beneficiary.vested_amount(current_time, extend_on_pause)? == 0,
+            WhitelistError::VestingAlreadyStarted
+        );
+
+        beneficiary.set_tranches(&tranches)?;
+
+        emit!(TranchesConfigured {
+            beneficiary: address,
+            tranche_count: beneficiary.tranche_count,
+            total_amount: beneficiary.amount,
+        });
 
-        Ok(current_claim)
+        Ok(())
     }
-}
 
-/// 白名单状态账户
-#[account]
-pub struct WhitelistState {
-    /// 合约管理员地址
-    pub authority: Pubkey,
-    
-    /// 白名单1地址
-    pub whitelist1: Pubkey,
-    
-    /// 白名单2地址
-    pub whitelist2: Pubkey,
-    
-    /// 白名单3地址
-    pub whitelist3: Pubkey,
-    
-    /// 总代币数量
-    pub total_amount: u64,
-    
-    /// 白名单1的释放金额
-    pub whitelist1_amount: u64,
-    
-    /// 白名单2的释放金额
-    pub whitelist2_amount: u64,
-    
-    /// 白名单3的释放金额
-    pub whitelist3_amount: u64,
-    
-    /// 开始释放时间
-    pub start_time: i64,
-    
-    /// 最后释放时间
-    pub last_release_time: i64,
-    
-    /// 总释放数量
-    pub total_released: u64,
+    /// 追加分配
+    /// 
+    /// 参数：
+    /// - beneficiary: 受益人地址
+    /// - amount: 追加数量
+    /// - start_time: 新分期的开始时间，从该时间起分 RELEASE_PERIOD 个月线性释放
+    /// 
+    /// 功能：
+    /// 1. 从管理员代币账户转入追加的代币
+    /// 2. 为受益人新增一个释放分期，并更新分配数量和总代币数量
+    /// 3. 校验合约代币余额不低于所有未领取的分配
+    pub fn add_allocation(
+        ctx: Context<AddAllocation>,
+        beneficiary: Pubkey,
+        amount: u64,
+        start_time: i64,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
 
-    /// 白名单1已领取数量
-    pub whitelist1_claimed: u64,
+        require!(
+            ctx.accounts.authority.key() == whitelist_state.authority,
+            WhitelistError::NotAuthority
+        );
+        require!(
+            ctx.accounts.token_account.owner == whitelist_state.key(),
+            WhitelistError::InvalidTokenAccount
+        );
+        require!(
+            start_time >= whitelist_state.start_time,
+            WhitelistError::InvalidTranche
+        );
 
-    /// 白名单2已领取数量
-    pub whitelist2_claimed: u64,
+        let index = whitelist_state
+            .beneficiary_index(&beneficiary)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
+        require!(
+            whitelist_state.beneficiaries[index].revoked_at == 0,
+            WhitelistError::AlreadyRevoked
+        );
 
-    /// 白名单3已领取数量
-    pub whitelist3_claimed: u64,
-}
+        let tranche = Tranche::linear(amount, start_time);
+        tranche.validate()?;
+        whitelist_state.beneficiaries[index].insert_tranche(tranche)?;
+        whitelist_state.beneficiaries[index].amount = whitelist_state.beneficiaries[index].amount
+            .checked_add(amount)
+            .ok_or(WhitelistError::ArithmeticOverflow)?;
+        whitelist_state.total_amount = whitelist_state.total_amount
+            .checked_add(amount)
+            .ok_or(WhitelistError::ArithmeticOverflow)?;
 
-impl WhitelistState {
-    /// 账户大小
-    pub const LEN: usize = 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8;
-}
+        // 转入追加的代币
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.authority_token_account.to_account_info(),
+                to: ctx.accounts.token_account.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
+            },
+        );
+        token::transfer(transfer_ctx, amount)?;
 
-/// 白名单合约错误类型
-#[error_code]
-pub enum WhitelistError {
-    /// 释放时间未到
-    #[msg("释放时间未到")]
-    ReleaseTimeNotReached,
-    
-    /// 没有更多释放计划
-    #[msg("没有更多释放计划")]
-    NoMoreReleases,
-    
-    /// 超出释放周期
-    #[msg("超出释放周期")]
-    ExceededReleasePeriod,
-    
-    /// 未授权的释放
-    #[msg("未授权的释放")]
-    UnauthorizedRelease,
-    
-    /// 算术溢出
-    #[msg("算术溢出")]
-    ArithmeticOverflow,
+        // 合约代币余额必须覆盖所有未领取的分配
+        ctx.accounts.token_account.reload()?;
+        require!(
+            ctx.accounts.token_account.amount >= ctx.accounts.whitelist_state.outstanding_amount()?,
+            WhitelistError::InsufficientVaultBalance
+        );
 
-    /// 无效的代币账户
-    #[msg("无效的代币账户")]
-    InvalidTokenAccount,
+        emit!(AllocationAdded {
+            beneficiary,
+            amount,
+            start_time,
+            total_amount: ctx.accounts.whitelist_state.total_amount,
+        });
 
-    /// 合约已经初始化
-    #[msg("合约已经初始化")]
-    AlreadyInitialized,
-}
+        Ok(())
+    }
 
-/// 白名单初始化事件
-#[event]
-pub struct WhitelistInitialized {
-    /// 合约管理员地址
-    pub authority: Pubkey,
-    
-    /// 总代币数量
-    pub total_amount: u64,
-    
-    /// 开始释放时间
-    pub start_time: i64,
-}
+    /// 把受益人的分配铸造为持仓凭证（NFT）
+    /// 
+    /// 功能：
+    /// 1. 由当前受益人签名选择开启，铸造 1 个精度为 0 的凭证到受益人的 ATA
+    /// 2. 铸造后收回铸币权限，凭证总量固定为 1
+    /// 3. 之后由凭证持有人释放代币，凭证转让即分配转让，无需管理员参与
+    /// 4. 铸造后不能再更换地址或登记提现地址，原有委托解除
+    pub fn tokenize_position(ctx: Context<TokenizePosition>) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
 
-/// 代币释放事件
-#[event]
-pub struct TokensReleased {
-    /// 用户地址
-    pub user: Pubkey,
-    
-    /// 释放数量
-    pub amount: u64,
-    
-    /// 总领取数量
-    pub total_claimed: u64,
-}
+        let beneficiary_address = ctx.accounts.beneficiary.key();
+        let index = whitelist_state
+            .beneficiary_index(&beneficiary_address)
+            .ok_or(WhitelistError::UnauthorizedRelease)?;
+        require!(
+            !whitelist_state.beneficiaries[index].is_tokenized(),
+            WhitelistError::PositionTokenized
+        );
+
+        whitelist_state.with_signer_seeds(|signer_seeds| {
+            // 铸造唯一的持仓凭证
+            token::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::MintTo {
+                        mint: ctx.accounts.position_mint.to_account_info(),
+                        to: ctx.accounts.position_token_account.to_account_info(),
+                        authority: whitelist_state.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                1,
+            )?;
+
+            // 收回铸币权限
+            token::set_authority(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::SetAuthority {
+                        current_authority: whitelist_state.to_account_info(),
+                        account_or_mint: ctx.accounts.position_mint.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                AuthorityType::MintTokens,
+                None,
+            )
+        })?;
+
+        let beneficiary = &mut whitelist_state.beneficiaries[index];
+        beneficiary.position_mint = ctx.accounts.position_mint.key();
+        beneficiary.pending_address = Pubkey::default();
+        beneficiary.pending_effective_time = 0;
+        beneficiary.withdrawal_address = Pubkey::default();
+        beneficiary.pending_withdrawal_address = Pubkey::default();
+        beneficiary.withdrawal_effective_time = 0;
+        beneficiary.clear_delegation();
+
+        emit!(PositionMinted {
+            beneficiary: beneficiary_address,
+            position_mint: beneficiary.position_mint,
+        });
+
+        Ok(())
+    }
+
+    /// 委托锁仓代币
+    /// 
+    /// 功能：
+    /// 1. 受益人（或持仓凭证持有人）把尚未释放的代币委托给质押或投票账户
+    /// 2. 代币仍由白名单合约保管，只记录委托关系，不通过 CPI 在外部程序中建立仓位
+    /// 3. 领取、撤销、铸造持仓凭证时委托自动解除，需要重新委托
+    pub fn delegate_locked_tokens(
+        ctx: Context<DelegateLockedTokens>,
+        delegate: Pubkey,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        let current_time = Clock::get()?.unix_timestamp;
+        let extend_on_pause = whitelist_state.extend_schedule_on_pause;
+
+        let index = whitelist_state.signer_index(
+            &ctx.accounts.beneficiary.key(),
+            ctx.accounts.position_token_account.as_ref(),
+        )?;
+        require!(delegate != Pubkey::default(), WhitelistError::InvalidDelegate);
+        require!(
+            whitelist_state.beneficiaries[index].revoked_at == 0,
+            WhitelistError::AlreadyRevoked
+        );
+
+        let amount = whitelist_state.beneficiaries[index]
+            .unvested_amount(current_time, extend_on_pause)?;
+        require!(amount > 0, WhitelistError::NothingToDelegate);
+
+        let beneficiary = &mut whitelist_state.beneficiaries[index];
+        beneficiary.delegate = delegate;
+
+        emit!(LockedTokensDelegated {
+            beneficiary: beneficiary.address,
+            delegate,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// 解除锁仓代币委托
+    pub fn undelegate_locked_tokens(ctx: Context<DelegateLockedTokens>) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+
+        let index = whitelist_state.signer_index(
+            &ctx.accounts.beneficiary.key(),
+            ctx.accounts.position_token_account.as_ref(),
+        )?;
+        require!(
+            whitelist_state.beneficiaries[index].delegate != Pubkey::default(),
+            WhitelistError::NotDelegated
+        );
+
+        whitelist_state.beneficiaries[index].clear_delegation();
+
+        Ok(())
+    }
+
+    /// 查询锁仓投票权重
+    /// 
+    /// 投票权重等于尚未释放的数量（分配数量减去已撤销和截至当前已释放的部分），
+    /// 每次查询实时计算，只在委托有效时计入委托账户；
+    /// 外部质押或投票程序可以通过 CPI 调用本接口读取返回值
+    #[view]
+    pub fn get_voting_weight(
+        ctx: Context<GetVestingSchedule>,
+        beneficiary: Pubkey,
+    ) -> Result<VotingWeight> {
+        let whitelist_state = &ctx.accounts.whitelist_state;
+        let index = whitelist_state
+            .beneficiary_index(&beneficiary)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
+
+        let weight = whitelist_state.beneficiaries[index]
+            .unvested_amount(Clock::get()?.unix_timestamp, whitelist_state.extend_schedule_on_pause)?;
+        Ok(VotingWeight {
+            beneficiary,
+            delegate: whitelist_state.beneficiaries[index].delegate,
+            weight,
+        })
+    }
+
+    /// 暂停受益人
+    /// 
+    /// 功能：
+    /// 1. 合规冻结（如等待 KYC）期间禁止该地址领取，不影响其他地址
+    /// 2. 按暂停策略决定释放计划是否顺延
+    pub fn pause_beneficiary(
+        ctx: Context<PauseBeneficiary>,
+        address: Pubkey,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            ctx.accounts.authority.key() == whitelist_state.authority,
+            WhitelistError::NotAuthority
+        );
+
+        let index = whitelist_state
+            .beneficiary_index(&address)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
+        let beneficiary = &mut whitelist_state.beneficiaries[index];
+        require!(beneficiary.paused_at == 0, WhitelistError::BeneficiaryPaused);
+
+        beneficiary.paused_at = current_time;
+
+        emit!(BeneficiaryPaused {
+            address,
+            paused_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    /// 恢复受益人
+    /// 
+    /// 功能：
+    /// 1. 解除暂停，恢复领取
+    /// 2. 暂停策略为顺延时，把本次暂停时长计入释放计划
+    pub fn resume_beneficiary(
+        ctx: Context<PauseBeneficiary>,
+        address: Pubkey,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        let current_time = Clock::get()?.unix_timestamp;
+        let extend_on_pause = whitelist_state.extend_schedule_on_pause;
+
+        require!(
+            ctx.accounts.authority.key() == whitelist_state.authority,
+            WhitelistError::NotAuthority
+        );
+
+        let index = whitelist_state
+            .beneficiary_index(&address)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
+        let beneficiary = &mut whitelist_state.beneficiaries[index];
+        require!(beneficiary.paused_at != 0, WhitelistError::BeneficiaryNotPaused);
+
+        // 撤销后的暂停时间不再影响已冻结的计划
+        let extension = if extend_on_pause {
+            let end_time = if beneficiary.revoked_at > 0 {
+                current_time.min(beneficiary.revoked_at)
+            } else {
+                current_time
+            };
+            (end_time - beneficiary.paused_at).max(0)
+        } else {
+            0
+        };
+        beneficiary.paused_duration = beneficiary.paused_duration
+            .checked_add(extension)
+            .ok_or(WhitelistError::ArithmeticOverflow)?;
+        beneficiary.paused_at = 0;
+
+        emit!(BeneficiaryResumed {
+            address,
+            resumed_at: current_time,
+            extension,
+        });
+
+        Ok(())
+    }
+
+    /// 设置暂停策略
+    /// 
+    /// 参数：
+    /// - extend_schedule_on_pause: true 表示暂停期间释放计划顺延，false 表示释放时间照常计算
+    /// 
+    /// 有受益人处于暂停中时不能修改
+    pub fn set_pause_policy(
+        ctx: Context<PauseBeneficiary>,
+        extend_schedule_on_pause: bool,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+
+        require!(
+            ctx.accounts.authority.key() == whitelist_state.authority,
+            WhitelistError::NotAuthority
+        );
+        require!(
+            whitelist_state.beneficiaries.iter().all(|b| b.paused_at == 0),
+            WhitelistError::BeneficiaryPaused
+        );
+
+        whitelist_state.extend_schedule_on_pause = extend_schedule_on_pause;
+
+        emit!(PausePolicyUpdated {
+            extend_schedule_on_pause,
+        });
+
+        Ok(())
+    }
+
+    /// 查询可领取代币数量
+    ///
+    /// 功能：
+    /// 1. 任意调用者可查询任意白名单地址
+    /// 2. 计算当前可领取的代币数量，没有可领取代币时返回 0
+    #[view]
+    pub fn get_claimable_amount(
+        ctx: Context<GetVestingSchedule>,
+        beneficiary: Pubkey,
+    ) -> Result<u64> {
+        let schedule = ctx.accounts.whitelist_state
+            .vesting_schedule(&beneficiary, Clock::get()?.unix_timestamp)?;
+
+        Ok(schedule.claimable_amount)
+    }
+
+    /// 查询白名单地址的完整释放计划
+    ///
+    /// 功能：
+    /// 1. 任意调用者可查询任意白名单地址
+    /// 2. 返回总分配、已释放、已领取、当前可领取、下次解锁时间和数量、最终解锁时间
+    /// 3. 只在地址不是白名单地址时报错，可领取为 0 不会报错
+    #[view]
+    pub fn get_vesting_schedule(
+        ctx: Context<GetVestingSchedule>,
+        beneficiary: Pubkey,
+    ) -> Result<VestingSchedule> {
+        ctx.accounts.whitelist_state
+            .vesting_schedule(&beneficiary, Clock::get()?.unix_timestamp)
+    }
+
+    /// 受益人更换地址
+    /// 
+    /// 功能：
+    /// 1. 当前受益人签名，把自己的位置转移到新地址
+    /// 2. 已领取和未领取的数量保持不变
+    /// 3. 清除该位置上未执行的恢复申请和提现地址
+    pub fn rotate_beneficiary(
+        ctx: Context<RotateBeneficiary>,
+        new_address: Pubkey,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        let old_address = ctx.accounts.beneficiary.key();
+
+        let index = whitelist_state
+            .beneficiary_index(&old_address)
+            .ok_or(WhitelistError::UnauthorizedRelease)?;
+        require!(
+            !whitelist_state.beneficiaries[index].is_tokenized(),
+            WhitelistError::PositionTokenized
+        );
+        whitelist_state.validate_new_beneficiary(&new_address)?;
+
+        whitelist_state.beneficiaries[index].rotate_to(new_address);
+
+        emit!(BeneficiaryRotated {
+            old_address,
+            new_address,
+            by_authority: false,
+        });
+
+        Ok(())
+    }
+
+    /// 管理员发起受益人地址恢复
+    /// 
+    /// 功能：
+    /// 1. 受益人丢失私钥时，由管理员申请把位置转移到新地址
+    /// 2. 需要等待 ROTATION_TIMELOCK 后才能执行
+    /// 3. 时间锁期间原受益人可以取消申请
+    pub fn propose_beneficiary_recovery(
+        ctx: Context<BeneficiaryRecovery>,
+        old_address: Pubkey,
+        new_address: Pubkey,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        require!(
+            ctx.accounts.authority.key() == whitelist_state.authority,
+            WhitelistError::NotAuthority
+        );
+
+        let index = whitelist_state
+            .beneficiary_index(&old_address)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
+        require!(
+            !whitelist_state.beneficiaries[index].is_tokenized(),
+            WhitelistError::PositionTokenized
+        );
+        whitelist_state.validate_new_beneficiary(&new_address)?;
+
+        let effective_time = Clock::get()?
+            .unix_timestamp
+            .checked_add(ROTATION_TIMELOCK)
+            .ok_or(WhitelistError::ArithmeticOverflow)?;
+
+        let beneficiary = &mut whitelist_state.beneficiaries[index];
+        beneficiary.pending_address = new_address;
+        beneficiary.pending_effective_time = effective_time;
+
+        emit!(BeneficiaryRecoveryProposed {
+            old_address,
+            new_address,
+            effective_time,
+        });
+
+        Ok(())
+    }
+
+    /// 管理员执行受益人地址恢复
+    /// 
+    /// 功能：
+    /// 1. 验证时间锁已经结束
+    /// 2. 把受益人位置转移到申请中的新地址
+    pub fn execute_beneficiary_recovery(
+        ctx: Context<BeneficiaryRecovery>,
+        old_address: Pubkey,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        require!(
+            ctx.accounts.authority.key() == whitelist_state.authority,
+            WhitelistError::NotAuthority
+        );
+
+        let index = whitelist_state
+            .beneficiary_index(&old_address)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
+        let new_address = whitelist_state.beneficiaries[index].pending_address;
+        require!(
+            new_address != Pubkey::default(),
+            WhitelistError::NoPendingRecovery
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= whitelist_state.beneficiaries[index].pending_effective_time,
+            WhitelistError::RecoveryTimelockNotElapsed
+        );
+        // 时间锁期间新地址可能已经被其他位置占用
+        whitelist_state.validate_new_beneficiary(&new_address)?;
+
+        whitelist_state.beneficiaries[index].rotate_to(new_address);
+
+        emit!(BeneficiaryRotated {
+            old_address,
+            new_address,
+            by_authority: true,
+        });
+
+        Ok(())
+    }
+
+    /// 取消受益人地址恢复
+    /// 
+    /// 功能：
+    /// 1. 管理员或当前受益人都可以取消
+    /// 2. 防止管理员私钥泄露时受益人位置被转走
+    pub fn cancel_beneficiary_recovery(
+        ctx: Context<CancelBeneficiaryRecovery>,
+        old_address: Pubkey,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        let signer = ctx.accounts.signer.key();
+        require!(
+            signer == whitelist_state.authority || signer == old_address,
+            WhitelistError::NotAuthority
+        );
+
+        let index = whitelist_state
+            .beneficiary_index(&old_address)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
+        let beneficiary = &mut whitelist_state.beneficiaries[index];
+        require!(
+            beneficiary.pending_address != Pubkey::default(),
+            WhitelistError::NoPendingRecovery
+        );
+
+        let new_address = beneficiary.pending_address;
+        beneficiary.pending_address = Pubkey::default();
+        beneficiary.pending_effective_time = 0;
+
+        emit!(BeneficiaryRecoveryCancelled {
+            old_address,
+            new_address,
+            cancelled_by: signer,
+        });
+
+        Ok(())
+    }
+
+    /// 撤销受益人
+    /// 
+    /// 功能：
+    /// 1. 在当前时间冻结受益人的释放计划
+    /// 2. 已释放部分仍可由受益人领取
+    /// 3. 未释放部分转回回收账户
+    /// 
+    /// 安全控制：
+    /// 1. 权限控制：只有管理员可以撤销
+    /// 2. 初始化时设置为不可撤销的受益人永远无法撤销
+    pub fn revoke_beneficiary(
+        ctx: Context<RevokeBeneficiary>,
+        address: Pubkey,
+    ) -> Result<()> {
+        let whitelist_state = &mut ctx.accounts.whitelist_state;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            ctx.accounts.authority.key() == whitelist_state.authority,
+            WhitelistError::NotAuthority
+        );
+        require!(
+            ctx.accounts.token_account.owner == whitelist_state.key(),
+            WhitelistError::InvalidTokenAccount
+        );
+        require!(
+            ctx.accounts.treasury_token_account.key() == whitelist_state.treasury,
+            WhitelistError::InvalidTreasury
+        );
+
+        let index = whitelist_state
+            .beneficiary_index(&address)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
+        let beneficiary = &whitelist_state.beneficiaries[index];
+        require!(beneficiary.revocable, WhitelistError::NotRevocable);
+        require!(beneficiary.revoked_at == 0, WhitelistError::AlreadyRevoked);
+
+        // 计算撤销时已释放的数量，剩余部分回收
+        let vested_amount = beneficiary
+            .vested_amount(current_time, whitelist_state.extend_schedule_on_pause)?;
+        let clawback_amount = beneficiary.amount
+            .checked_sub(vested_amount)
+            .ok_or(WhitelistError::ArithmeticOverflow)?;
+
+        if clawback_amount > 0 {
+            transfer_from_vault(
+                whitelist_state,
+                &ctx.accounts.token_account,
+                ctx.accounts.treasury_token_account.to_account_info(),
+                &ctx.accounts.token_program,
+                clawback_amount,
+            )?;
+        }
+
+        let beneficiary = &mut whitelist_state.beneficiaries[index];
+        beneficiary.revoked_at = current_time;
+        beneficiary.revoked_amount = clawback_amount;
+        beneficiary.clear_delegation();
+
+        emit!(BeneficiaryRevoked {
+            address,
+            vested_amount,
+            clawback_amount,
+            revoked_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    /// 升级白名单状态账户布局
+    /// 
+    /// 功能：
+    /// 1. 读取首次部署（版本 1）布局的白名单状态账户：三个白名单地址、释放金额和已领取数量为固定字段
+    /// 2. 扩容到当前布局大小，不足的租金由管理员补足
+    /// 3. 原地写入当前布局，白名单地址、释放金额、已领取数量和开始释放时间保持不变
+    /// 4. 版本 1 没有撤销功能，升级后的受益人均不可撤销；记录回收账户并创建释放记录账户
+    /// 
+    /// 安全控制：
+    /// 1. 权限控制：只有状态账户中记录的管理员可以升级
+    /// 2. 已是当前版本的账户不能重复升级
+    pub fn migrate_state(ctx: Context<MigrateState>) -> Result<()> {
+        let state_info = ctx.accounts.whitelist_state.to_account_info();
+        require!(
+            state_info.owner == ctx.program_id,
+            WhitelistError::InvalidStateAccount
+        );
+
+        // 按版本 1 布局读取，判别符与当前版本相同，按账户大小区分版本
+        let legacy = {
+            let data = state_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == WhitelistState::DISCRIMINATOR,
+                WhitelistError::InvalidStateAccount
+            );
+            require!(
+                data.len() == 8 + WhitelistStateV1::LEN,
+                WhitelistError::AlreadyMigrated
+            );
+            let mut body: &[u8] = &data[8..];
+            WhitelistStateV1::deserialize(&mut body)
+                .map_err(|_| error!(WhitelistError::InvalidStateAccount))?
+        };
+        require!(
+            ctx.accounts.authority.key() == legacy.authority,
+            WhitelistError::NotAuthority
+        );
+
+        // 验证回收账户与白名单代币为同一种代币
+        require!(
+            ctx.accounts.treasury_token_account.mint == ctx.accounts.token_account.mint,
+            WhitelistError::InvalidTreasury
+        );
+
+        let state = legacy.upgrade(
+            ctx.accounts.treasury_token_account.key(),
+            ctx.bumps.whitelist_state,
+        );
+        let new_len = 8 + WhitelistState::space(state.beneficiaries.len());
+
+        // 补足扩容后的租金
+        if new_len > state_info.data_len() {
+            let required = Rent::get()?
+                .minimum_balance(new_len)
+                .saturating_sub(state_info.lamports());
+            if required > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.authority.to_account_info(),
+                            to: state_info.clone(),
+                        },
+                    ),
+                    required,
+                )?;
+            }
+            state_info.realloc(new_len, false)?;
+        }
+
+        // 清空后按当前布局写入
+        {
+            let mut data = state_info.try_borrow_mut_data()?;
+            data.fill(0);
+            let mut writer: &mut [u8] = &mut data;
+            state.try_serialize(&mut writer)?;
+        }
+
+        // 版本 1 没有释放记录账户，确认需要升级后再创建，重复升级时先返回 AlreadyMigrated
+        let history_info = ctx.accounts.release_history.to_account_info();
+        let history_space = 8 + ReleaseHistory::space(BENEFICIARY_COUNT);
+        let state_key = state_info.key();
+        let history_seeds: &[&[u8]] = &[
+            b"release_history",
+            state_key.as_ref(),
+            &[ctx.bumps.release_history],
+        ];
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: history_info.clone(),
+                },
+                &[history_seeds],
+            ),
+            Rent::get()?.minimum_balance(history_space),
+            history_space as u64,
+            ctx.program_id,
+        )?;
+        let mut release_history = ReleaseHistory {
+            whitelist_state: Pubkey::default(),
+            total_records: 0,
+            released_through: Vec::new(),
+            records: Vec::new(),
+        };
+        release_history.init(state_key, BENEFICIARY_COUNT);
+        let mut data = history_info.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data;
+        release_history.try_serialize(&mut writer)?;
+
+        emit!(StateMigrated {
+            whitelist_state: state_info.key(),
+            from_version: 1,
+            to_version: STATE_VERSION,
+        });
+
+        Ok(())
+    }
+
+    /// 结束白名单并回收租金
+    /// 
+    /// 功能：
+    /// 1. 验证所有受益人都已领取全部分配（已撤销的按撤销后的分配计算）
+    /// 2. 合约代币账户中剩余的零头转入回收账户
+    /// 3. 关闭合约代币账户、释放记录账户和白名单状态账户，租金返还给管理员
+    /// 4. 创建关闭记录，之后不能再用同一地址（白名单或同一归属池编号）重新创建
+    /// 
+    /// 安全控制：
+    /// 1. 权限控制：只有管理员可以执行
+    /// 2. 任何受益人还有未领取的代币时无法执行
+    pub fn finalize_whitelist(ctx: Context<FinalizeWhitelist>) -> Result<()> {
+        let whitelist_state = &ctx.accounts.whitelist_state;
+
+        require!(
+            ctx.accounts.authority.key() == whitelist_state.authority,
+            WhitelistError::NotAuthority
+        );
+        require!(
+            ctx.accounts.token_account.owner == whitelist_state.key(),
+            WhitelistError::InvalidTokenAccount
+        );
+        require!(
+            ctx.accounts.treasury_token_account.key() == whitelist_state.treasury,
+            WhitelistError::InvalidTreasury
+        );
+        require!(
+            ctx.accounts.release_history.whitelist_state == whitelist_state.key(),
+            WhitelistError::InvalidReleaseHistory
+        );
+        require!(
+            whitelist_state.outstanding_amount()? == 0,
+            WhitelistError::VestingNotComplete
+        );
+
+        // 剩余零头转入回收账户
+        let dust_amount = ctx.accounts.token_account.amount;
+        if dust_amount > 0 {
+            transfer_from_vault(
+                whitelist_state,
+                &ctx.accounts.token_account,
+                ctx.accounts.treasury_token_account.to_account_info(),
+                &ctx.accounts.token_program,
+                dust_amount,
+            )?;
+        }
+
+        // 关闭合约代币账户，租金返还给管理员
+        whitelist_state.with_signer_seeds(|signer_seeds| {
+            token::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::CloseAccount {
+                    account: ctx.accounts.token_account.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: whitelist_state.to_account_info(),
+                },
+                signer_seeds,
+            ))
+        })?;
+
+        let closed_state = &mut ctx.accounts.closed_state;
+        closed_state.whitelist_state = whitelist_state.key();
+        closed_state.closed_at = Clock::get()?.unix_timestamp;
+
+        emit!(WhitelistFinalized {
+            whitelist_state: whitelist_state.key(),
+            total_released: whitelist_state.total_released,
+            dust_amount,
+        });
+
+        Ok(())
+    }
+}
+
+/// 计算受益人本次可领取金额并转入 destination
+///
+/// 返回本次释放数量，没有可领取代币时返回 0 且不做任何修改
+fn release_vested<'info>(
+    whitelist_state: &mut Account<'info, WhitelistState>,
+    release_history: &mut ReleaseHistory,
+    index: usize,
+    vault: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    current_time: i64,
+) -> Result<u64> {
+    // 计算应领取的总金额（已撤销的受益人按撤销时间冻结）
+    let beneficiary = &whitelist_state.beneficiaries[index];
+    let total_should_claim = beneficiary
+        .vested_amount(current_time, whitelist_state.extend_schedule_on_pause)?;
+
+    // 计算本次可领取金额
+    let current_claim = total_should_claim
+        .checked_sub(beneficiary.claimed)
+        .ok_or(WhitelistError::ArithmeticOverflow)?;
+
+    if current_claim == 0 {
+        return Ok(0);
+    }
+
+    // 转移代币
+    transfer_from_vault(whitelist_state, vault, destination, token_program, current_claim)?;
+
+    // 更新状态，领取后委托自动解除
+    whitelist_state.beneficiaries[index].claimed = total_should_claim;
+    whitelist_state.beneficiaries[index].clear_delegation();
+    whitelist_state.last_release_time = current_time;
+    whitelist_state.total_released = whitelist_state.total_released
+        .checked_add(current_claim)
+        .ok_or(WhitelistError::ArithmeticOverflow)?;
+
+    // 写入释放记录，覆盖区间按释放计划时钟记录，首次释放从最早分期的开始时间起算
+    let beneficiary = &whitelist_state.beneficiaries[index];
+    let to_time = beneficiary.vesting_time(current_time, whitelist_state.extend_schedule_on_pause);
+    let from_time = match release_history.released_through[index] {
+        0 => beneficiary.active_tranches()[0].start_time,
+        released_through => released_through,
+    };
+    release_history.push(index, ReleaseRecord {
+        timestamp: current_time,
+        beneficiary: beneficiary.address,
+        amount: current_claim,
+        from_time,
+        to_time,
+    });
+
+    emit!(TokensReleased {
+        user: whitelist_state.beneficiaries[index].address,
+        amount: current_claim,
+        total_claimed: total_should_claim,
+    });
+
+    Ok(current_claim)
+}
+
+/// 按 50%/30%/20% 拆分白名单总代币数量
+/// 
+/// 前两份向下取整，取整余数全部归最后一个白名单，三份之和始终等于 total_amount
+pub fn split_whitelist_amount(total_amount: u64) -> Result<[u64; BENEFICIARY_COUNT]> {
+    let share = |numerator: u128| -> Result<u64> {
+        u64::try_from(total_amount as u128 * numerator / 10)
+            .map_err(|_| error!(WhitelistError::ArithmeticOverflow))
+    };
+    let whitelist1_amount = share(5)?; // 2.5%
+    let whitelist2_amount = share(3)?; // 1.5%
+    let whitelist3_amount = total_amount
+        .checked_sub(whitelist1_amount)
+        .and_then(|v| v.checked_sub(whitelist2_amount))
+        .ok_or(WhitelistError::ArithmeticOverflow)?; // 1.0%，含取整余数
+
+    Ok([whitelist1_amount, whitelist2_amount, whitelist3_amount])
+}
+
+/// 从白名单合约（或归属池）的代币账户转出代币，由状态账户 PDA 签名
+fn transfer_from_vault<'info>(
+    whitelist_state: &Account<'info, WhitelistState>,
+    from: &Account<'info, TokenAccount>,
+    to: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    whitelist_state.with_signer_seeds(|signer_seeds| {
+        let transfer_ctx = CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::Transfer {
+                from: from.to_account_info(),
+                to,
+                authority: whitelist_state.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        token::transfer(transfer_ctx, amount)
+    })
+}
+
+/// 白名单状态账户
+/// 
+/// 白名单和通过 create_vesting_pool 创建的归属池共用此结构
+#[account]
+pub struct WhitelistState {
+    /// 合约管理员地址
+    pub authority: Pubkey,
+    
+    /// 受益人列表，白名单依次为白名单1、2、3
+    pub beneficiaries: Vec<Beneficiary>,
+    
+    /// 总代币数量
+    pub total_amount: u64,
+    
+    /// 开始释放时间
+    pub start_time: i64,
+    
+    /// 最后释放时间
+    pub last_release_time: i64,
+    
+    /// 总释放数量
+    pub total_released: u64,
+
+    /// 撤销后未释放代币的回收账户
+    pub treasury: Pubkey,
+
+    /// 白名单状态账户 PDA bump
+    pub bump: u8,
+
+    /// 暂停策略：true 表示暂停期间释放计划顺延，false 表示释放时间照常计算
+    pub extend_schedule_on_pause: bool,
+
+    /// 是否为归属池，白名单实例为 false
+    pub is_pool: bool,
+
+    /// 归属池编号，白名单实例为 0
+    pub pool_id: u64,
+
+    /// 归属池名称，白名单实例为空
+    pub name: String,
+
+    /// 账户布局版本
+    pub version: u8,
+
+    /// 预留空间
+    pub reserved: [u8; STATE_RESERVED_LEN],
+}
+
+impl WhitelistState {
+    /// 账户大小，随受益人数量变化
+    pub const fn space(beneficiary_count: usize) -> usize {
+        32 + 4 + Beneficiary::LEN * beneficiary_count + 8 + 8 + 8 + 8 + 32 + 1 + 1 + 1 + 8 + 4 + MAX_POOL_NAME_LEN
+            + 1 + STATE_RESERVED_LEN
+    }
+
+    /// 使用状态账户的 PDA 种子签名
+    /// 
+    /// 白名单种子为 ["whitelist_state"]，归属池种子为 ["vesting_pool", 管理员, 池编号]
+    pub fn with_signer_seeds<R>(&self, f: impl FnOnce(&[&[&[u8]]]) -> R) -> R {
+        let bump = [self.bump];
+        if self.is_pool {
+            let pool_id = self.pool_id.to_le_bytes();
+            f(&[&[b"vesting_pool", self.authority.as_ref(), &pool_id, &bump]])
+        } else {
+            f(&[&[b"whitelist_state", &bump]])
+        }
+    }
+
+    /// 查找地址对应的受益人位置
+    pub fn beneficiary_index(&self, address: &Pubkey) -> Option<usize> {
+        self.beneficiaries.iter().position(|b| b.address == *address)
+    }
+
+    /// 计算受益人在 current_time 的释放计划
+    pub fn vesting_schedule(&self, address: &Pubkey, current_time: i64) -> Result<VestingSchedule> {
+        let index = self
+            .beneficiary_index(address)
+            .ok_or(WhitelistError::AddressNotWhitelisted)?;
+        let beneficiary = &self.beneficiaries[index];
+
+        let extend_on_pause = self.extend_schedule_on_pause;
+        let vested_amount = beneficiary.vested_amount(current_time, extend_on_pause)?;
+        let claimable_amount = beneficiary.claimable_amount(current_time, extend_on_pause)?;
+
+        // 分期时间换算为实际时间的偏移：已延长的暂停时间，
+        // 当前暂停中且暂停延长计划时，按立即恢复估算
+        let schedule_time = beneficiary.vesting_time(current_time, extend_on_pause);
+        let frozen = extend_on_pause && beneficiary.paused_at > 0;
+        let mut offset = beneficiary.paused_duration;
+        if frozen {
+            offset = offset.saturating_add(current_time - beneficiary.paused_at);
+        }
+
+        // 最终解锁时间，已撤销的受益人以撤销时间为准
+        let mut final_unlock_time = beneficiary
+            .active_tranches()
+            .iter()
+            .map(|t| t.final_unlock_time())
+            .max()
+            .unwrap_or(self.start_time)
+            .saturating_add(offset);
+        if beneficiary.revoked_at > 0 {
+            final_unlock_time = final_unlock_time.min(beneficiary.revoked_at);
+        }
+
+        // 所有分期中最近的一个解锁点，已全部解锁或暂停冻结时为 0
+        let (mut next_unlock_time, mut next_unlock_amount) = (0, 0);
+        if let Some(unlock_time) = beneficiary
+            .active_tranches()
+            .iter()
+            .filter_map(|t| t.next_unlock_time(schedule_time))
+            .min()
+        {
+            let unlock_time = unlock_time.saturating_add(offset);
+            if !frozen && unlock_time <= final_unlock_time {
+                next_unlock_time = unlock_time;
+                next_unlock_amount = beneficiary
+                    .vested_amount(unlock_time, extend_on_pause)?
+                    .checked_sub(vested_amount)
+                    .ok_or(WhitelistError::ArithmeticOverflow)?;
+            }
+        }
+
+        Ok(VestingSchedule {
+            beneficiary: *address,
+            total_allocation: beneficiary.amount
+                .checked_sub(beneficiary.revoked_amount)
+                .ok_or(WhitelistError::ArithmeticOverflow)?,
+            vested_amount,
+            claimed_amount: beneficiary.claimed,
+            claimable_amount,
+            next_unlock_time,
+            next_unlock_amount,
+            final_unlock_time,
+        })
+    }
+
+    /// 所有受益人尚未领取的分配总额（已撤销的部分不计入）
+    pub fn outstanding_amount(&self) -> Result<u64> {
+        let mut outstanding: u64 = 0;
+        for beneficiary in self.beneficiaries.iter() {
+            let remaining = beneficiary.amount
+                .checked_sub(beneficiary.revoked_amount)
+                .and_then(|v| v.checked_sub(beneficiary.claimed))
+                .ok_or(WhitelistError::ArithmeticOverflow)?;
+            outstanding = outstanding
+                .checked_add(remaining)
+                .ok_or(WhitelistError::ArithmeticOverflow)?;
+        }
+        Ok(outstanding)
+    }
+
+    /// 根据签名者确定受益人位置
+    /// 
+    /// 已铸造持仓凭证的分配需传入签名者持有的凭证账户，其他分配按受益人地址查找
+    pub fn signer_index(
+        &self,
+        signer: &Pubkey,
+        position_token_account: Option<&Account<TokenAccount>>,
+    ) -> Result<usize> {
+        match position_token_account {
+            Some(position_token_account) => {
+                require!(
+                    position_token_account.owner == *signer,
+                    WhitelistError::NotPositionHolder
+                );
+                let index = self
+                    .position_index(&position_token_account.mint)
+                    .ok_or(WhitelistError::NotPositionHolder)?;
+                self.verify_position_holder(index, position_token_account)?;
+                Ok(index)
+            }
+            None => {
+                let index = self
+                    .beneficiary_index(signer)
+                    .ok_or(WhitelistError::AddressNotWhitelisted)?;
+                require!(
+                    !self.beneficiaries[index].is_tokenized(),
+                    WhitelistError::PositionTokenized
+                );
+                Ok(index)
+            }
+        }
+    }
+
+    /// 查找持仓凭证对应的受益人位置
+    pub fn position_index(&self, position_mint: &Pubkey) -> Option<usize> {
+        if *position_mint == Pubkey::default() {
+            return None;
+        }
+        self.beneficiaries.iter().position(|b| b.position_mint == *position_mint)
+    }
+
+    /// 验证代币账户持有该位置的持仓凭证
+    pub fn verify_position_holder(
+        &self,
+        index: usize,
+        position_token_account: &TokenAccount,
+    ) -> Result<()> {
+        let beneficiary = &self.beneficiaries[index];
+        require!(
+            beneficiary.is_tokenized()
+                && position_token_account.mint == beneficiary.position_mint
+                && position_token_account.amount == 1,
+            WhitelistError::NotPositionHolder
+        );
+        Ok(())
+    }
+
+    /// 验证新受益人地址有效且不与现有受益人重复
+    pub fn validate_new_beneficiary(&self, new_address: &Pubkey) -> Result<()> {
+        require!(
+            *new_address != Pubkey::default(),
+            WhitelistError::InvalidBeneficiaryAddress
+        );
+        require!(
+            self.beneficiary_index(new_address).is_none(),
+            WhitelistError::DuplicateBeneficiary
+        );
+        Ok(())
+    }
+}
+
+/// 版本 1（首次部署）的白名单状态账户布局
+/// 
+/// 三个白名单的地址、释放金额和已领取数量为固定字段，没有版本号，只用于 migrate_state 读取旧账户
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct WhitelistStateV1 {
+    pub authority: Pubkey,
+    pub whitelist1: Pubkey,
+    pub whitelist2: Pubkey,
+    pub whitelist3: Pubkey,
+    pub total_amount: u64,
+    pub whitelist1_amount: u64,
+    pub whitelist2_amount: u64,
+    pub whitelist3_amount: u64,
+    pub start_time: i64,
+    pub last_release_time: i64,
+    pub total_released: u64,
+    pub whitelist1_claimed: u64,
+    pub whitelist2_claimed: u64,
+    pub whitelist3_claimed: u64,
+}
+
+impl WhitelistStateV1 {
+    /// 账户大小
+    pub const LEN: usize = 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8;
+
+    /// 升级为当前布局
+    /// 
+    /// 白名单地址、释放金额、已领取数量和开始释放时间原样保留，
+    /// 释放计划仍为从开始释放时间起分 RELEASE_PERIOD 个月线性释放
+    pub fn upgrade(self, treasury: Pubkey, bump: u8) -> WhitelistState {
+        let whitelists = [
+            (self.whitelist1, self.whitelist1_amount, self.whitelist1_claimed),
+            (self.whitelist2, self.whitelist2_amount, self.whitelist2_claimed),
+            (self.whitelist3, self.whitelist3_amount, self.whitelist3_claimed),
+        ];
+        let beneficiaries = whitelists
+            .iter()
+            .map(|&(address, amount, claimed)| {
+                let mut beneficiary = Beneficiary::new(address, amount, false, self.start_time);
+                beneficiary.claimed = claimed;
+                beneficiary
+            })
+            .collect();
+
+        WhitelistState {
+            authority: self.authority,
+            beneficiaries,
+            total_amount: self.total_amount,
+            start_time: self.start_time,
+            last_release_time: self.last_release_time,
+            total_released: self.total_released,
+            treasury,
+            bump,
+            extend_schedule_on_pause: false,
+            is_pool: false,
+            pool_id: 0,
+            name: String::new(),
+            version: STATE_VERSION,
+            reserved: [0; STATE_RESERVED_LEN],
+        }
+    }
+}
+
+/// 归属池的受益人分配，由 create_vesting_pool 传入
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct PoolAllocation {
+    /// 受益人地址
+    pub address: Pubkey,
+
+    /// 分配数量
+    pub amount: u64,
+
+    /// 是否可撤销
+    pub revocable: bool,
+
+    /// 释放分期，为空时从开始释放时间起分 RELEASE_PERIOD 个月线性释放
+    pub tranches: Vec<Tranche>,
+}
+
+/// 释放记录账户
+/// 
+/// 只追加的环形缓冲，保存最近 RELEASE_HISTORY_CAPACITY 条释放记录，
+/// 写满后覆盖最早的记录；完整历史仍可通过 TokensReleased 事件获取
+#[account]
+pub struct ReleaseHistory {
+    /// 所属的白名单状态账户
+    pub whitelist_state: Pubkey,
+
+    /// 累计写入的记录数量，包括已被覆盖的记录
+    pub total_records: u64,
+
+    /// 各受益人位置已释放到的释放计划时间，未释放过为 0
+    pub released_through: Vec<i64>,
+
+    /// 释放记录，写满后按 total_records 取模覆盖
+    pub records: Vec<ReleaseRecord>,
+}
+
+impl ReleaseHistory {
+    /// 账户大小，随受益人数量变化
+    pub const fn space(beneficiary_count: usize) -> usize {
+        32 + 8 + 4 + 8 * beneficiary_count + 4 + ReleaseRecord::LEN * RELEASE_HISTORY_CAPACITY
+    }
+
+    /// 初始化释放记录账户
+    pub fn init(&mut self, whitelist_state: Pubkey, beneficiary_count: usize) {
+        self.whitelist_state = whitelist_state;
+        self.released_through = vec![0; beneficiary_count];
+        self.records = Vec::with_capacity(RELEASE_HISTORY_CAPACITY);
+    }
+
+    /// 追加一条释放记录
+    pub fn push(&mut self, index: usize, record: ReleaseRecord) {
+        self.released_through[index] = record.to_time;
+
+        if self.records.len() < RELEASE_HISTORY_CAPACITY {
+            self.records.push(record);
+        } else {
+            let slot = (self.total_records % RELEASE_HISTORY_CAPACITY as u64) as usize;
+            self.records[slot] = record;
+        }
+        self.total_records = self.total_records.saturating_add(1);
+    }
+
+    /// 按时间从早到晚返回保存的释放记录
+    pub fn ordered_records(&self) -> Vec<ReleaseRecord> {
+        if self.records.len() < RELEASE_HISTORY_CAPACITY {
+            return self.records.clone();
+        }
+        let head = (self.total_records % RELEASE_HISTORY_CAPACITY as u64) as usize;
+        self.records[head..]
+            .iter()
+            .chain(self.records[..head].iter())
+            .copied()
+            .collect()
+    }
+
+    /// 按时间顺序返回某个受益人地址的释放记录
+    pub fn records_for(&self, beneficiary: &Pubkey) -> Vec<ReleaseRecord> {
+        self.ordered_records()
+            .into_iter()
+            .filter(|record| record.beneficiary == *beneficiary)
+            .collect()
+    }
+}
+
+/// 状态账户关闭记录
+/// 
+/// 结束白名单或归属池时创建，由 ["closed_state", 状态账户] 推导并永久保留，
+/// 创建状态账户时校验该地址为空，防止已结束的白名单或归属池编号被重新创建
+#[account]
+pub struct ClosedState {
+    /// 已关闭的状态账户
+    pub whitelist_state: Pubkey,
+
+    /// 关闭时间
+    pub closed_at: i64,
+}
+
+impl ClosedState {
+    /// 结构大小
+    pub const LEN: usize = 32 + 8;
+}
+
+/// 单次释放记录
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub struct ReleaseRecord {
+    /// 释放时间
+    pub timestamp: i64,
+
+    /// 释放时的受益人地址
+    pub beneficiary: Pubkey,
+
+    /// 释放数量
+    pub amount: u64,
+
+    /// 本次释放覆盖的起始时间（不含），按释放计划时钟计算
+    pub from_time: i64,
+
+    /// 本次释放覆盖的截止时间（含），按释放计划时钟计算
+    pub to_time: i64,
+}
+
+impl ReleaseRecord {
+    /// 结构大小
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 8;
+
+    /// 本次释放覆盖的时长（秒）
+    pub fn duration_covered(&self) -> i64 {
+        self.to_time.saturating_sub(self.from_time)
+    }
+}
+
+/// 客户端读取释放记录
+/// 
+/// 传入释放记录账户的原始数据（如 RpcClient::get_account_data 的返回值），
+/// 按时间从早到晚返回保存的释放记录
+pub fn decode_release_history(data: &[u8]) -> Result<Vec<ReleaseRecord>> {
+    let mut data = data;
+    let release_history = ReleaseHistory::try_deserialize(&mut data)?;
+    Ok(release_history.ordered_records())
+}
+
+/// 白名单地址的释放计划，由 get_vesting_schedule 返回
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct VestingSchedule {
+    /// 受益人地址
+    pub beneficiary: Pubkey,
+
+    /// 总分配数量，已撤销时不含回收部分
+    pub total_allocation: u64,
+
+    /// 截至当前已释放数量
+    pub vested_amount: u64,
+
+    /// 已领取数量
+    pub claimed_amount: u64,
+
+    /// 当前可领取数量
+    pub claimable_amount: u64,
+
+    /// 下次解锁时间，已全部解锁时为 0
+    pub next_unlock_time: i64,
+
+    /// 下次解锁数量
+    pub next_unlock_amount: u64,
+
+    /// 最终解锁时间
+    pub final_unlock_time: i64,
+}
+
+/// 锁仓投票权重，由 get_voting_weight 返回
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct VotingWeight {
+    /// 受益人地址
+    pub beneficiary: Pubkey,
+
+    /// 委托账户，未委托时为默认地址
+    pub delegate: Pubkey,
+
+    /// 投票权重，等于尚未释放的数量
+    pub weight: u64,
+}
+
+/// 白名单受益人
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct Beneficiary {
+    /// 受益人地址
+    pub address: Pubkey,
+    
+    /// 释放金额
+    pub amount: u64,
+    
+    /// 已领取数量
+    pub claimed: u64,
+    
+    /// 管理员恢复申请中的新地址，未申请时为默认地址
+    pub pending_address: Pubkey,
+    
+    /// 恢复申请可执行时间
+    pub pending_effective_time: i64,
+
+    /// 是否可撤销，初始化时设置
+    pub revocable: bool,
+
+    /// 撤销时间，未撤销时为 0
+    pub revoked_at: i64,
+
+    /// 撤销时回收的未释放数量
+    pub revoked_amount: u64,
+
+    /// 已生效的提现地址（代币账户），未设置时为默认地址
+    pub withdrawal_address: Pubkey,
+
+    /// 等待生效的提现地址
+    pub pending_withdrawal_address: Pubkey,
+
+    /// 等待中的提现地址生效时间
+    pub withdrawal_effective_time: i64,
+
+    /// 释放分期，前 tranche_count 个有效
+    pub tranches: [Tranche; MAX_TRANCHES],
+
+    /// 有效分期数量
+    pub tranche_count: u8,
+
+    /// 暂停时间，未暂停时为 0
+    pub paused_at: i64,
+
+    /// 暂停累计延长的释放时间（秒）
+    pub paused_duration: i64,
+
+    /// 持仓凭证 mint，未铸造时为默认地址
+    pub position_mint: Pubkey,
+
+    /// 锁仓代币的委托账户，未委托时为默认地址
+    pub delegate: Pubkey,
+}
+
+impl Beneficiary {
+    /// 结构大小
+    pub const LEN: usize =
+        32 + 8 + 8 + 32 + 8 + 1 + 8 + 8 + 32 + 32 + 8 + Tranche::LEN * MAX_TRANCHES + 1 + 8 + 8 + 32 + 32;
+
+    /// 创建受益人，默认从 start_time 起分 RELEASE_PERIOD 个月线性释放
+    pub fn new(address: Pubkey, amount: u64, revocable: bool, start_time: i64) -> Self {
+        let mut tranches = [Tranche::default(); MAX_TRANCHES];
+        tranches[0] = Tranche::linear(amount, start_time);
+
+        Self {
+            address,
+            amount,
+            claimed: 0,
+            pending_address: Pubkey::default(),
+            pending_effective_time: 0,
+            revocable,
+            revoked_at: 0,
+            revoked_amount: 0,
+            withdrawal_address: Pubkey::default(),
+            pending_withdrawal_address: Pubkey::default(),
+            withdrawal_effective_time: 0,
+            tranches,
+            tranche_count: 1,
+            paused_at: 0,
+            paused_duration: 0,
+            position_mint: Pubkey::default(),
+            delegate: Pubkey::default(),
+        }
+    }
+
+    /// 替换全部释放分期
+    /// 
+    /// 分期需按开始时间排序，总额必须等于分配数量
+    pub fn set_tranches(&mut self, tranches: &[Tranche]) -> Result<()> {
+        require!(
+            !tranches.is_empty() && tranches.len() <= MAX_TRANCHES,
+            WhitelistError::InvalidTranche
+        );
+        let mut total_amount: u64 = 0;
+        for (i, tranche) in tranches.iter().enumerate() {
+            tranche.validate()?;
+            if i > 0 {
+                require!(
+                    tranche.start_time >= tranches[i - 1].start_time,
+                    WhitelistError::InvalidTranche
+                );
+            }
+            total_amount = total_amount
+                .checked_add(tranche.amount)
+                .ok_or(WhitelistError::ArithmeticOverflow)?;
+        }
+        require!(
+            total_amount == self.amount,
+            WhitelistError::TrancheAmountMismatch
+        );
+
+        self.tranches = [Tranche::default(); MAX_TRANCHES];
+        self.tranches[..tranches.len()].copy_from_slice(tranches);
+        self.tranche_count = tranches.len() as u8;
+        Ok(())
+    }
+
+    /// 解除锁仓代币委托
+    pub fn clear_delegation(&mut self) {
+        if self.delegate == Pubkey::default() {
+            return;
+        }
+        emit!(DelegationRevoked {
+            beneficiary: self.address,
+            delegate: self.delegate,
+        });
+        self.delegate = Pubkey::default();
+    }
+
+    /// 截至 current_time 已释放但尚未领取的数量
+    pub fn claimable_amount(&self, current_time: i64, extend_on_pause: bool) -> Result<u64> {
+        self.vested_amount(current_time, extend_on_pause)?
+            .checked_sub(self.claimed)
+            .ok_or(WhitelistError::ArithmeticOverflow.into())
+    }
+
+    /// 截至 current_time 尚未释放的数量，即锁仓投票权重
+    pub fn unvested_amount(&self, current_time: i64, extend_on_pause: bool) -> Result<u64> {
+        let vested_amount = self.vested_amount(current_time, extend_on_pause)?;
+        self.amount
+            .checked_sub(self.revoked_amount)
+            .and_then(|v| v.checked_sub(vested_amount))
+            .ok_or(WhitelistError::ArithmeticOverflow.into())
+    }
+
+    /// 是否已铸造持仓凭证
+    pub fn is_tokenized(&self) -> bool {
+        self.position_mint != Pubkey::default()
+    }
+
+    /// 有效的释放分期
+    pub fn active_tranches(&self) -> &[Tranche] {
+        &self.tranches[..self.tranche_count as usize]
+    }
+
+    /// 按开始时间顺序插入新的分期
+    pub fn insert_tranche(&mut self, tranche: Tranche) -> Result<()> {
+        let count = self.tranche_count as usize;
+        require!(count < MAX_TRANCHES, WhitelistError::TrancheLimitReached);
+
+        let position = self.active_tranches()
+            .iter()
+            .position(|t| t.start_time > tranche.start_time)
+            .unwrap_or(count);
+        self.tranches.copy_within(position..count, position + 1);
+        self.tranches[position] = tranche;
+        self.tranche_count += 1;
+
+        Ok(())
+    }
+
+    /// 把受益人位置转移到新地址
+    /// 
+    /// 原地址登记的恢复申请和提现地址一并清除，已领取数量保留
+    pub fn rotate_to(&mut self, new_address: Pubkey) {
+        self.address = new_address;
+        self.pending_address = Pubkey::default();
+        self.pending_effective_time = 0;
+        self.withdrawal_address = Pubkey::default();
+        self.pending_withdrawal_address = Pubkey::default();
+        self.withdrawal_effective_time = 0;
+    }
+
+    /// 等待中的提现地址到期后生效
+    pub fn apply_pending_withdrawal_address(&mut self, current_time: i64) {
+        if self.pending_withdrawal_address != Pubkey::default()
+            && current_time >= self.withdrawal_effective_time
+        {
+            self.withdrawal_address = self.pending_withdrawal_address;
+            self.pending_withdrawal_address = Pubkey::default();
+            self.withdrawal_effective_time = 0;
+        }
+    }
+
+    /// 判断代币账户是否为 current_time 时已生效的提现地址
+    pub fn is_withdrawal_address(&self, token_account: &Pubkey, current_time: i64) -> bool {
+        if *token_account == Pubkey::default() {
+            return false;
+        }
+        if *token_account == self.withdrawal_address {
+            return true;
+        }
+        *token_account == self.pending_withdrawal_address
+            && current_time >= self.withdrawal_effective_time
+    }
+
+    /// 把实际时间换算为释放计划时间
+    /// 
+    /// 撤销后计划冻结在撤销时间，并扣除暂停延长的时间；
+    /// extend_on_pause 为 true 且暂停中时，计划冻结在暂停时间
+    pub fn vesting_time(&self, current_time: i64, extend_on_pause: bool) -> i64 {
+        let mut vesting_time = current_time;
+        if self.revoked_at > 0 {
+            vesting_time = vesting_time.min(self.revoked_at);
+        }
+        if extend_on_pause && self.paused_at > 0 {
+            vesting_time = vesting_time.min(self.paused_at);
+        }
+        vesting_time.saturating_sub(self.paused_duration)
+    }
+
+    /// 计算截至 current_time 所有分期已释放的总数量
+    pub fn vested_amount(&self, current_time: i64, extend_on_pause: bool) -> Result<u64> {
+        let end_time = self.vesting_time(current_time, extend_on_pause);
+
+        let mut vested_amount: u64 = 0;
+        for tranche in self.active_tranches() {
+            vested_amount = vested_amount
+                .checked_add(tranche.vested_amount(end_time)?)
+                .ok_or(WhitelistError::ArithmeticOverflow)?;
+        }
+
+        Ok(vested_amount)
+    }
+}
+
+/// 释放分期
+///
+/// 从 start_time 起每 step 秒解锁一次，共 duration / step 次；
+/// start_time + cliff 之前不解锁，期间到期的部分在 cliff 结束时一起解锁。
+/// duration 为 0 时在 start_time + cliff 一次性解锁（如 TGE 解锁）。
+#[derive(Debug, Default, Clone, Copy, AnchorSerialize, AnchorDeserialize)]
+pub struct Tranche {
+    /// 分期数量
+    pub amount: u64,
+
+    /// 开始时间
+    pub start_time: i64,
+
+    /// 锁定期（秒）
+    pub cliff: i64,
+
+    /// 释放期（秒）
+    pub duration: i64,
+
+    /// 解锁间隔（秒）
+    pub step: i64,
+}
+
+impl Tranche {
+    /// 结构大小
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 8;
+
+    /// 从 start_time 起分 RELEASE_PERIOD 个月按月线性释放
+    pub fn linear(amount: u64, start_time: i64) -> Self {
+        Self {
+            amount,
+            start_time,
+            cliff: 0,
+            duration: SECONDS_PER_MONTH * RELEASE_PERIOD as i64,
+            step: SECONDS_PER_MONTH,
+        }
+    }
+
+    /// 验证分期参数
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.amount > 0 && self.cliff >= 0 && self.duration >= 0,
+            WhitelistError::InvalidTranche
+        );
+        if self.duration > 0 {
+            require!(
+                self.step > 0 && self.step <= self.duration,
+                WhitelistError::InvalidTranche
+            );
+        }
+        Ok(())
+    }
+
+    /// 解锁次数
+    fn total_steps(&self) -> i64 {
+        if self.duration == 0 {
+            1
+        } else {
+            self.duration / self.step
+        }
+    }
+
+    /// 计算截至 current_time 本分期已释放的数量
+    /// 
+    /// 每次解锁金额向下取整，取整余数在最后一次解锁时释放，全部解锁后等于 amount
+    pub fn vested_amount(&self, current_time: i64) -> Result<u64> {
+        if current_time < self.start_time.saturating_add(self.cliff) {
+            return Ok(0);
+        }
+
+        let total_steps = self.total_steps();
+        let steps_passed = if self.duration == 0 {
+            1
+        } else {
+            ((current_time - self.start_time) / self.step).min(total_steps)
+        };
+        if steps_passed >= total_steps {
+            return Ok(self.amount);
+        }
+
+        // 计算每次解锁金额
+        let step_release = self.amount
+            .checked_div(total_steps as u64)
+            .ok_or(WhitelistError::ArithmeticOverflow)?;
+
+        let vested_amount = step_release
+            .checked_mul(steps_passed as u64)
+            .ok_or(WhitelistError::ArithmeticOverflow)?;
+
+        Ok(vested_amount)
+    }
+
+    /// current_time 之后的下一个解锁时间，已全部解锁时返回 None
+    pub fn next_unlock_time(&self, current_time: i64) -> Option<i64> {
+        let cliff_end = self.start_time.saturating_add(self.cliff);
+        if self.duration == 0 {
+            return (current_time < cliff_end).then_some(cliff_end);
+        }
+
+        let next_step = if current_time < self.start_time {
+            1
+        } else {
+            (current_time - self.start_time) / self.step + 1
+        };
+        if next_step > self.total_steps() {
+            return None;
+        }
+
+        Some(self.start_time.saturating_add(next_step * self.step).max(cliff_end))
+    }
+
+    /// 最终解锁时间
+    pub fn final_unlock_time(&self) -> i64 {
+        let last_step = if self.duration == 0 {
+            0
+        } else {
+            self.total_steps() * self.step
+        };
+        self.start_time.saturating_add(last_step.max(self.cliff))
+    }
+}
+
+/// 白名单合约错误类型
+#[error_code]
+pub enum WhitelistError {
+    /// 释放时间未到
+    #[msg("释放时间未到")]
+    ReleaseTimeNotReached,
+    
+    /// 没有更多释放计划
+    #[msg("没有更多释放计划")]
+    NoMoreReleases,
+    
+    /// 超出释放周期
+    #[msg("超出释放周期")]
+    ExceededReleasePeriod,
+    
+    /// 未授权的释放
+    #[msg("未授权的释放")]
+    UnauthorizedRelease,
+    
+    /// 算术溢出
+    #[msg("算术溢出")]
+    ArithmeticOverflow,
+
+    /// 无效的代币账户
+    #[msg("无效的代币账户")]
+    InvalidTokenAccount,
+
+    /// 合约已经初始化
+    #[msg("合约已经初始化")]
+    AlreadyInitialized,
+
+    /// 非管理员操作
+    #[msg("只有管理员可以执行此操作")]
+    NotAuthority,
+
+    /// 地址不在白名单中
+    #[msg("地址不在白名单中")]
+    AddressNotWhitelisted,
+
+    /// 无效的受益人地址
+    #[msg("无效的受益人地址")]
+    InvalidBeneficiaryAddress,
+
+    /// 受益人地址重复
+    #[msg("受益人地址已在白名单中")]
+    DuplicateBeneficiary,
+
+    /// 没有待执行的恢复申请
+    #[msg("没有待执行的恢复申请")]
+    NoPendingRecovery,
+
+    /// 恢复时间锁未结束
+    #[msg("恢复时间锁未结束")]
+    RecoveryTimelockNotElapsed,
+
+    /// 无效的回收账户
+    #[msg("无效的回收账户")]
+    InvalidTreasury,
+
+    /// 受益人不可撤销
+    #[msg("受益人不可撤销")]
+    NotRevocable,
+
+    /// 受益人已经撤销
+    #[msg("受益人已经撤销")]
+    AlreadyRevoked,
+
+    /// 受益人代币账户不是其关联代币账户
+    #[msg("受益人代币账户不是其关联代币账户")]
+    InvalidBeneficiaryTokenAccount,
+
+    /// 接收账户既不是受益人的关联代币账户，也不是已生效的提现地址
+    #[msg("接收账户不是受益人的关联代币账户或已生效的提现地址")]
+    InvalidDestination,
+
+    /// 无效的释放分期
+    #[msg("无效的释放分期")]
+    InvalidTranche,
+
+    /// 分期总额与分配数量不一致
+    #[msg("分期总额与分配数量不一致")]
+    TrancheAmountMismatch,
+
+    /// 受益人已有代币释放，不能修改分期
+    #[msg("受益人已有代币释放，不能修改分期")]
+    VestingAlreadyStarted,
+
+    /// 分期数量已达上限
+    #[msg("分期数量已达上限")]
+    TrancheLimitReached,
+
+    /// 合约代币余额不足以覆盖未领取的分配
+    #[msg("合约代币余额不足以覆盖未领取的分配")]
+    InsufficientVaultBalance,
+
+    /// 受益人已暂停
+    #[msg("受益人已暂停")]
+    BeneficiaryPaused,
+
+    /// 受益人未暂停
+    #[msg("受益人未暂停")]
+    BeneficiaryNotPaused,
+
+    /// 分配已铸造持仓凭证，需由凭证持有人操作
+    #[msg("分配已铸造持仓凭证，需由凭证持有人操作")]
+    PositionTokenized,
+
+    /// 不是持仓凭证的持有人
+    #[msg("不是持仓凭证的持有人")]
+    NotPositionHolder,
+
+    /// 无效的委托账户
+    #[msg("无效的委托账户")]
+    InvalidDelegate,
+
+    /// 没有可委托的锁仓代币
+    #[msg("没有可委托的锁仓代币")]
+    NothingToDelegate,
+
+    /// 未委托锁仓代币
+    #[msg("未委托锁仓代币")]
+    NotDelegated,
+
+    /// 释放记录账户不属于该白名单
+    #[msg("释放记录账户不属于该白名单")]
+    InvalidReleaseHistory,
+
+    /// 归属池名称过长
+    #[msg("归属池名称过长")]
+    PoolNameTooLong,
+
+    /// 归属池受益人数量无效
+    #[msg("归属池受益人数量无效")]
+    InvalidBeneficiaryCount,
+
+    /// 还有受益人未领取全部分配
+    #[msg("还有受益人未领取全部分配")]
+    VestingNotComplete,
+
+    /// 无效的状态账户
+    #[msg("无效的状态账户")]
+    InvalidStateAccount,
+
+    /// 状态账户已是当前版本
+    #[msg("状态账户已是当前版本")]
+    AlreadyMigrated,
+
+    /// 状态账户已结束，不能重新创建
+    #[msg("状态账户已结束，不能重新创建")]
+    StateClosed,
+}
+
+/// 白名单初始化事件
+#[event]
+pub struct WhitelistInitialized {
+    /// 合约管理员地址
+    pub authority: Pubkey,
+    
+    /// 总代币数量
+    pub total_amount: u64,
+    
+    /// 开始释放时间
+    pub start_time: i64,
+}
+
+/// 代币释放事件
+#[event]
+pub struct TokensReleased {
+    /// 用户地址
+    pub user: Pubkey,
+    
+    /// 释放数量
+    pub amount: u64,
+    
+    /// 总领取数量
+    pub total_claimed: u64,
+}
+
+/// 受益人地址更换事件
+#[event]
+pub struct BeneficiaryRotated {
+    /// 原地址
+    pub old_address: Pubkey,
+    
+    /// 新地址
+    pub new_address: Pubkey,
+    
+    /// 是否由管理员恢复
+    pub by_authority: bool,
+}
+
+/// 受益人地址恢复申请事件
+#[event]
+pub struct BeneficiaryRecoveryProposed {
+    /// 原地址
+    pub old_address: Pubkey,
+    
+    /// 新地址
+    pub new_address: Pubkey,
+    
+    /// 可执行时间
+    pub effective_time: i64,
+}
+
+/// 受益人地址恢复取消事件
+#[event]
+pub struct BeneficiaryRecoveryCancelled {
+    /// 原地址
+    pub old_address: Pubkey,
+    
+    /// 被取消的新地址
+    pub new_address: Pubkey,
+    
+    /// 取消人
+    pub cancelled_by: Pubkey,
+}
+
+/// 受益人撤销事件
+#[event]
+pub struct BeneficiaryRevoked {
+    /// 受益人地址
+    pub address: Pubkey,
+
+    /// 撤销时已释放数量
+    pub vested_amount: u64,
+
+    /// 回收数量
+    pub clawback_amount: u64,
+
+    /// 撤销时间
+    pub revoked_at: i64,
+}
+
+/// 提现地址变更事件
+#[event]
+pub struct WithdrawalAddressUpdated {
+    /// 受益人地址
+    pub beneficiary: Pubkey,
+
+    /// 新的提现地址，默认地址表示清除
+    pub withdrawal_address: Pubkey,
+
+    /// 生效时间
+    pub effective_time: i64,
+}
+
+/// 释放分期设置事件
+#[event]
+pub struct TranchesConfigured {
+    /// 受益人地址
+    pub beneficiary: Pubkey,
+
+    /// 分期数量
+    pub tranche_count: u8,
+
+    /// 分期总额
+    pub total_amount: u64,
+}
+
+/// 追加分配事件
+#[event]
+pub struct AllocationAdded {
+    /// 受益人地址
+    pub beneficiary: Pubkey,
+
+    /// 追加数量
+    pub amount: u64,
+
+    /// 新分期开始时间
+    pub start_time: i64,
+
+    /// 追加后的总代币数量
+    pub total_amount: u64,
+}
+
+/// 受益人暂停事件
+#[event]
+pub struct BeneficiaryPaused {
+    /// 受益人地址
+    pub address: Pubkey,
+
+    /// 暂停时间
+    pub paused_at: i64,
+}
+
+/// 受益人恢复事件
+#[event]
+pub struct BeneficiaryResumed {
+    /// 受益人地址
+    pub address: Pubkey,
+
+    /// 恢复时间
+    pub resumed_at: i64,
+
+    /// 本次暂停使释放计划顺延的时间（秒）
+    pub extension: i64,
+}
+
+/// 暂停策略变更事件
+#[event]
+pub struct PausePolicyUpdated {
+    /// 暂停期间释放计划是否顺延
+    pub extend_schedule_on_pause: bool,
+}
+
+/// 持仓凭证铸造事件
+#[event]
+pub struct PositionMinted {
+    /// 受益人地址
+    pub beneficiary: Pubkey,
+
+    /// 持仓凭证 mint
+    pub position_mint: Pubkey,
+}
+
+/// 锁仓代币委托事件
+#[event]
+pub struct LockedTokensDelegated {
+    /// 受益人地址
+    pub beneficiary: Pubkey,
+
+    /// 委托账户
+    pub delegate: Pubkey,
+
+    /// 委托数量
+    pub amount: u64,
+}
+
+/// 锁仓代币委托解除事件
+#[event]
+pub struct DelegationRevoked {
+    /// 受益人地址
+    pub beneficiary: Pubkey,
+
+    /// 原委托账户
+    pub delegate: Pubkey,
+}
+
+/// 归属池创建事件
+#[event]
+pub struct VestingPoolCreated {
+    /// 归属池状态账户
+    pub pool: Pubkey,
+
+    /// 归属池管理员
+    pub authority: Pubkey,
+
+    /// 归属池编号
+    pub pool_id: u64,
+
+    /// 归属池名称
+    pub name: String,
+
+    /// 总代币数量
+    pub total_amount: u64,
+
+    /// 受益人数量
+    pub beneficiary_count: u8,
+}
+
+/// 白名单结束事件
+#[event]
+pub struct WhitelistFinalized {
+    /// 白名单状态账户
+    pub whitelist_state: Pubkey,
+
+    /// 总释放数量
+    pub total_released: u64,
+
+    /// 转入回收账户的零头
+    pub dust_amount: u64,
+}
+
+/// 状态账户升级事件
+#[event]
+pub struct StateMigrated {
+    /// 状态账户
+    pub whitelist_state: Pubkey,
+
+    /// 原版本
+    pub from_version: u8,
+
+    /// 新版本
+    pub to_version: u8,
+}
 
 /// 初始化白名单所需的账户
 #[derive(Accounts)]
@@ -348,7 +2619,7 @@ pub struct InitializeWhitelist<'info> {
     #[account(
         init,
         payer = authority,
-        space = 8 + WhitelistState::LEN,
+        space = 8 + WhitelistState::space(BENEFICIARY_COUNT),
         seeds = [b"whitelist_state"],
         bump
     )]
@@ -363,9 +2634,97 @@ pub struct InitializeWhitelist<'info> {
     /// 在初始化时，我们会读取这个账户的代币数量作为总分配量
     #[account(mut)]
     pub token_account: Account<'info, TokenAccount>,
-    
+
+    /// 回收账户，撤销受益人时接收未释放的代币
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    /// 释放记录账户
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ReleaseHistory::space(BENEFICIARY_COUNT),
+        seeds = [b"release_history", whitelist_state.key().as_ref()],
+        bump
+    )]
+    pub release_history: Box<Account<'info, ReleaseHistory>>,
+
+    /// CHECK: 关闭记录地址，只校验尚未创建
+    #[account(
+        seeds = [b"closed_state", whitelist_state.key().as_ref()],
+        bump,
+        constraint = closed_state.data_is_empty() @ WhitelistError::StateClosed
+    )]
+    pub closed_state: UncheckedAccount<'info>,
+
+    /// 系统程序
+    pub system_program: Program<'info, System>,
+}
+
+/// 创建归属池所需的账户
+#[derive(Accounts)]
+#[instruction(pool_id: u64, name: String, start_time: i64, allocations: Vec<PoolAllocation>)]
+pub struct CreateVestingPool<'info> {
+    /// 归属池状态账户
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + WhitelistState::space(allocations.len()),
+        seeds = [b"vesting_pool", authority.key().as_ref(), &pool_id.to_le_bytes()],
+        bump
+    )]
+    pub pool_state: Box<Account<'info, WhitelistState>>,
+
+    /// 归属池的代币账户，由归属池状态账户持有
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"vesting_vault", pool_state.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = pool_state,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// 释放记录账户
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ReleaseHistory::space(allocations.len()),
+        seeds = [b"release_history", pool_state.key().as_ref()],
+        bump
+    )]
+    pub release_history: Box<Account<'info, ReleaseHistory>>,
+
+    /// CHECK: 关闭记录地址，只校验尚未创建
+    #[account(
+        seeds = [b"closed_state", pool_state.key().as_ref()],
+        bump,
+        constraint = closed_state.data_is_empty() @ WhitelistError::StateClosed
+    )]
+    pub closed_state: UncheckedAccount<'info>,
+
+    /// 归属代币
+    pub mint: Box<Account<'info, Mint>>,
+
+    /// 管理员的代币账户，分配的代币从这里转出
+    #[account(mut)]
+    pub authority_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// 回收账户，撤销受益人时接收未释放的代币
+    pub treasury_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// 归属池管理员
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// 代币程序
+    pub token_program: Program<'info, Token>,
+
     /// 系统程序
     pub system_program: Program<'info, System>,
+
+    /// 租金系统
+    pub rent: Sysvar<'info, Rent>,
 }
 
 /// 释放代币所需的账户
@@ -379,10 +2738,17 @@ pub struct ReleaseTokens<'info> {
     #[account(mut)]
     pub token_account: Account<'info, TokenAccount>,
     
-    /// 用户的代币账户
+    /// 用户的代币账户，必须是用户的 ATA 或已生效的提现地址
     #[account(mut)]
     pub user_token_account: Account<'info, TokenAccount>,
     
+    /// 释放记录账户
+    #[account(mut)]
+    pub release_history: Box<Account<'info, ReleaseHistory>>,
+    
+    /// 持仓凭证代币账户，释放已铸造凭证的分配时传入
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+    
     /// 用户签名
     #[account(mut)]
     pub user: Signer<'info>,
@@ -391,12 +2757,306 @@ pub struct ReleaseTokens<'info> {
     pub token_program: Program<'info, Token>,
 }
 
-/// 查询可领取代币数量所需的账户
+/// 设置释放分期所需的账户
+#[derive(Accounts)]
+pub struct SetBeneficiaryTranches<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+
+    /// 合约管理员
+    pub authority: Signer<'info>,
+}
+
+/// 追加分配所需的账户
+#[derive(Accounts)]
+pub struct AddAllocation<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+
+    /// 白名单合约的代币账户
+    #[account(mut)]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// 管理员的代币账户，追加的代币从这里转出
+    #[account(mut)]
+    pub authority_token_account: Account<'info, TokenAccount>,
+
+    /// 合约管理员
+    pub authority: Signer<'info>,
+
+    /// 代币程序
+    pub token_program: Program<'info, Token>,
+}
+
+/// 铸造持仓凭证所需的账户
+#[derive(Accounts)]
+pub struct TokenizePosition<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+
+    /// 持仓凭证 mint，由白名单状态账户作为铸币权限
+    #[account(
+        init,
+        payer = beneficiary,
+        seeds = [b"position_mint", whitelist_state.key().as_ref(), beneficiary.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = whitelist_state,
+    )]
+    pub position_mint: Account<'info, Mint>,
+
+    /// 受益人接收持仓凭证的关联代币账户
+    #[account(
+        init,
+        payer = beneficiary,
+        associated_token::mint = position_mint,
+        associated_token::authority = beneficiary,
+    )]
+    pub position_token_account: Account<'info, TokenAccount>,
+
+    /// 当前受益人签名
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+
+    /// 代币程序
+    pub token_program: Program<'info, Token>,
+
+    /// 关联代币程序
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    /// 系统程序
+    pub system_program: Program<'info, System>,
+
+    /// 租金系统
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// 委托和解除委托锁仓代币所需的账户
+#[derive(Accounts)]
+pub struct DelegateLockedTokens<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+
+    /// 持仓凭证代币账户，已铸造凭证的分配需传入
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// 受益人或持仓凭证持有人签名
+    pub beneficiary: Signer<'info>,
+}
+
+/// 暂停、恢复受益人和设置暂停策略所需的账户
+#[derive(Accounts)]
+pub struct PauseBeneficiary<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+
+    /// 合约管理员
+    pub authority: Signer<'info>,
+}
+
+/// 查询释放计划所需的账户
+#[derive(Accounts)]
+pub struct GetVestingSchedule<'info> {
+    /// 白名单状态账户
+    pub whitelist_state: Account<'info, WhitelistState>,
+}
+
+/// 受益人更换地址所需的账户
+#[derive(Accounts)]
+pub struct RotateBeneficiary<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+    
+    /// 当前受益人签名
+    pub beneficiary: Signer<'info>,
+}
+
+/// 管理员恢复受益人地址所需的账户
 #[derive(Accounts)]
-pub struct GetClaimableAmount<'info> {
+pub struct BeneficiaryRecovery<'info> {
     /// 白名单状态账户
+    #[account(mut)]
     pub whitelist_state: Account<'info, WhitelistState>,
     
-    /// 查询用户地址
-    pub user: Signer<'info>,
-} 
\ No newline at end of file
+    /// 合约管理员
+    pub authority: Signer<'info>,
+}
+
+/// 取消受益人地址恢复所需的账户
+#[derive(Accounts)]
+pub struct CancelBeneficiaryRecovery<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+    
+    /// 管理员或当前受益人
+    pub signer: Signer<'info>,
+}
+
+/// 撤销受益人所需的账户
+#[derive(Accounts)]
+pub struct RevokeBeneficiary<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+
+    /// 白名单合约的代币账户
+    #[account(mut)]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// 回收账户
+    #[account(mut)]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    /// 合约管理员
+    pub authority: Signer<'info>,
+
+    /// 代币程序
+    pub token_program: Program<'info, Token>,
+}
+
+/// 设置提现地址所需的账户
+#[derive(Accounts)]
+pub struct SetWithdrawalAddress<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+
+    /// 受益人签名
+    pub beneficiary: Signer<'info>,
+}
+
+/// 代任意白名单地址释放代币所需的账户
+#[derive(Accounts)]
+pub struct CrankRelease<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+
+    /// 白名单合约的代币账户
+    #[account(mut)]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// 受益人的关联代币账户，已铸造持仓凭证时为凭证持有人的关联代币账户
+    #[account(mut)]
+    pub beneficiary_token_account: Account<'info, TokenAccount>,
+
+    /// 释放记录账户
+    #[account(mut)]
+    pub release_history: Box<Account<'info, ReleaseHistory>>,
+
+    /// 持仓凭证代币账户，释放已铸造凭证的分配时传入
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// 调用者，任何人都可以
+    pub payer: Signer<'info>,
+
+    /// 代币程序
+    pub token_program: Program<'info, Token>,
+}
+
+/// 一次为所有白名单地址释放代币所需的账户
+/// 
+/// remaining_accounts：按顺序传入每个受益人的关联代币账户（可写）
+#[derive(Accounts)]
+pub struct CrankReleaseAll<'info> {
+    /// 白名单状态账户
+    #[account(mut)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+
+    /// 白名单合约的代币账户
+    #[account(mut)]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// 释放记录账户
+    #[account(mut)]
+    pub release_history: Box<Account<'info, ReleaseHistory>>,
+
+    /// 调用者，任何人都可以
+    pub payer: Signer<'info>,
+
+    /// 代币程序
+    pub token_program: Program<'info, Token>,
+}
+
+/// 结束白名单所需的账户
+#[derive(Accounts)]
+pub struct FinalizeWhitelist<'info> {
+    /// 白名单状态账户，关闭后租金返还给管理员
+    #[account(mut, close = authority)]
+    pub whitelist_state: Account<'info, WhitelistState>,
+
+    /// 白名单合约的代币账户，关闭后租金返还给管理员
+    #[account(mut)]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// 释放记录账户，关闭后租金返还给管理员
+    #[account(mut, close = authority)]
+    pub release_history: Box<Account<'info, ReleaseHistory>>,
+
+    /// 回收账户，接收剩余零头
+    #[account(mut)]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    /// 关闭记录，永久保留，防止状态账户被重新创建
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ClosedState::LEN,
+        seeds = [b"closed_state", whitelist_state.key().as_ref()],
+        bump
+    )]
+    pub closed_state: Account<'info, ClosedState>,
+
+    /// 合约管理员
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// 代币程序
+    pub token_program: Program<'info, Token>,
+
+    /// 系统程序
+    pub system_program: Program<'info, System>,
+}
+
+/// 升级状态账户布局所需的账户
+#[derive(Accounts)]
+pub struct MigrateState<'info> {
+    /// CHECK: 旧版本布局无法按当前结构反序列化，所有者、判别符和管理员在指令中校验
+    #[account(
+        mut,
+        seeds = [b"whitelist_state"],
+        bump
+    )]
+    pub whitelist_state: UncheckedAccount<'info>,
+
+    /// 状态账户的管理员，补足扩容租金
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// 白名单合约的代币账户
+    #[account(
+        constraint = token_account.owner == whitelist_state.key() @ WhitelistError::InvalidTokenAccount
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// 回收账户，撤销受益人时接收未释放的代币
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: 释放记录账户，在指令中确认需要升级后创建
+    #[account(
+        mut,
+        seeds = [b"release_history", whitelist_state.key().as_ref()],
+        bump
+    )]
+    pub release_history: UncheckedAccount<'info>,
+
+    /// 系统程序
+    pub system_program: Program<'info, System>,
+}
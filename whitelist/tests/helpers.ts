@@ -0,0 +1,292 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Whitelist, IDL } from "../target/types/whitelist";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
+import {
+  ACCOUNT_SIZE,
+  AccountLayout,
+  getAssociatedTokenAddressSync,
+  MINT_SIZE,
+  MintLayout,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import { AddedAccount, Clock, ProgramTestContext, startAnchor } from "solana-bankrun";
+import { BankrunProvider } from "anchor-bankrun";
+
+// 不依赖本地验证器的测试共用：预置账户数据、调整链上时间
+
+export const PROGRAM_ID = new PublicKey("6iadRi4ps7itomsTNa34RikS6hkmx2z5Ls1h9EqLPu1y");
+export const SECONDS_PER_MONTH = 30 * 24 * 60 * 60;
+export const SECONDS_PER_DAY = 24 * 60 * 60;
+
+export function bn(value: number | bigint) {
+  return new anchor.BN(value.toString());
+}
+
+/// 释放分期参数
+export type TrancheArgs = {
+  amount: anchor.BN;
+  startTime: anchor.BN;
+  cliff: anchor.BN;
+  duration: anchor.BN;
+  step: anchor.BN;
+};
+
+/// 归属池地址：状态账户、代币账户、释放记录账户
+export function poolAddresses(authority: PublicKey, poolId: anchor.BN) {
+  const [poolState] = PublicKey.findProgramAddressSync(
+    [Buffer.from("vesting_pool"), authority.toBuffer(), poolId.toArrayLike(Buffer, "le", 8)],
+    PROGRAM_ID
+  );
+  const [vault] = PublicKey.findProgramAddressSync([Buffer.from("vesting_vault"), poolState.toBuffer()], PROGRAM_ID);
+  const [releaseHistory] = PublicKey.findProgramAddressSync(
+    [Buffer.from("release_history"), poolState.toBuffer()],
+    PROGRAM_ID
+  );
+  return { poolState, vault, releaseHistory, closedState: closedStateAddress(poolState) };
+}
+
+/// 状态账户的关闭记录地址
+export function closedStateAddress(state: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from("closed_state"), state.toBuffer()], PROGRAM_ID)[0];
+}
+
+/// 有余额的系统账户
+export function fundedAccount(address: PublicKey): AddedAccount {
+  return {
+    address,
+    info: {
+      lamports: 10 * LAMPORTS_PER_SOL,
+      data: Buffer.alloc(0),
+      owner: SystemProgram.programId,
+      executable: false,
+    },
+  };
+}
+
+/// 代币 mint 账户
+export function mintAccount(address: PublicKey, mintAuthority: PublicKey, supply: bigint): AddedAccount {
+  const data = Buffer.alloc(MINT_SIZE);
+  MintLayout.encode(
+    {
+      mintAuthorityOption: 1,
+      mintAuthority,
+      supply,
+      decimals: 6,
+      isInitialized: true,
+      freezeAuthorityOption: 0,
+      freezeAuthority: PublicKey.default,
+    },
+    data
+  );
+  return {
+    address,
+    info: { lamports: LAMPORTS_PER_SOL, data, owner: TOKEN_PROGRAM_ID, executable: false },
+  };
+}
+
+/// 代币账户
+export function tokenAccount(address: PublicKey, mint: PublicKey, owner: PublicKey, amount: bigint): AddedAccount {
+  const data = Buffer.alloc(ACCOUNT_SIZE);
+  AccountLayout.encode(
+    {
+      mint,
+      owner,
+      amount,
+      delegateOption: 0,
+      delegate: PublicKey.default,
+      state: 1,
+      isNativeOption: 0,
+      isNative: BigInt(0),
+      delegatedAmount: BigInt(0),
+      closeAuthorityOption: 0,
+      closeAuthority: PublicKey.default,
+    },
+    data
+  );
+  return {
+    address,
+    info: { lamports: LAMPORTS_PER_SOL, data, owner: TOKEN_PROGRAM_ID, executable: false },
+  };
+}
+
+/// 读取代币账户余额
+export async function tokenBalance(context: ProgramTestContext, address: PublicKey): Promise<bigint> {
+  const account = await context.banksClient.getAccount(address);
+  return AccountLayout.decode(account.data).amount;
+}
+
+/// 加载预置账户启动程序，payer 为 provider 钱包
+export async function startWhitelist(accounts: AddedAccount[]) {
+  const context = await startAnchor(".", [], accounts);
+  const provider = new BankrunProvider(context);
+  const program = new Program<Whitelist>(IDL, PROGRAM_ID, provider as any);
+  return { context, provider, program };
+}
+
+/// 把链上时间调整到 unixTimestamp
+export async function warpTo(context: ProgramTestContext, unixTimestamp: number) {
+  // 同时推进 slot，避免相同交易因 blockhash 不变被当作重复交易
+  const clock = await context.banksClient.getClock();
+  const slot = clock.slot + BigInt(1);
+  context.warpToSlot(slot);
+  context.setClock(
+    new Clock(
+      slot,
+      clock.epochStartTimestamp,
+      clock.epoch,
+      clock.leaderScheduleEpoch,
+      BigInt(unixTimestamp)
+    )
+  );
+}
+
+/// 在 startTime 之前创建一个归属池，受益人为新生成的密钥对
+///
+/// - allocations: 每个受益人的分配数量、是否可撤销和释放分期（为空时默认 36 个月线性释放）
+/// - withoutAta: 不预先创建关联代币账户的受益人序号
+/// - holders: 受益人之外需要预先创建关联代币账户的地址
+/// - mint: 归属代币地址，需要预置同一代币的其他账户时传入
+/// - extraTokens: 管理员代币账户在分配之外额外持有的数量，用于追加分配
+/// - accounts: 其他预置账户
+export async function setupPool(options: {
+  startTime: number;
+  allocations: { amount: bigint; revocable?: boolean; tranches?: TrancheArgs[] }[];
+  withoutAta?: number[];
+  holders?: PublicKey[];
+  mint?: PublicKey;
+  extraTokens?: bigint;
+  accounts?: AddedAccount[];
+}) {
+  const authority = Keypair.generate();
+  const beneficiaries = options.allocations.map(() => Keypair.generate());
+  const mint = options.mint ?? Keypair.generate().publicKey;
+  const authorityTokenAccount = Keypair.generate().publicKey;
+  const treasuryTokenAccount = Keypair.generate().publicKey;
+  const total = options.allocations.reduce((sum, a) => sum + a.amount, BigInt(0));
+  const extraTokens = options.extraTokens ?? BigInt(0);
+  const withoutAta = options.withoutAta ?? [];
+
+  const ata = (owner: PublicKey) => getAssociatedTokenAddressSync(mint, owner, true);
+  const poolId = new anchor.BN(1);
+  const { poolState, vault, releaseHistory, closedState } = poolAddresses(authority.publicKey, poolId);
+
+  const { context, provider, program } = await startWhitelist([
+    fundedAccount(authority.publicKey),
+    ...beneficiaries.map((b) => fundedAccount(b.publicKey)),
+    mintAccount(mint, authority.publicKey, total + extraTokens),
+    tokenAccount(authorityTokenAccount, mint, authority.publicKey, total + extraTokens),
+    tokenAccount(treasuryTokenAccount, mint, authority.publicKey, BigInt(0)),
+    ...beneficiaries
+      .filter((_, i) => !withoutAta.includes(i))
+      .map((b) => tokenAccount(ata(b.publicKey), mint, b.publicKey, BigInt(0))),
+    ...(options.holders ?? []).map((owner) => tokenAccount(ata(owner), mint, owner, BigInt(0))),
+    ...(options.accounts ?? []),
+  ]);
+
+  await warpTo(context, options.startTime - 1);
+  await program.methods
+    .createVestingPool(
+      poolId,
+      "test",
+      bn(options.startTime),
+      options.allocations.map((a, i) => ({
+        address: beneficiaries[i].publicKey,
+        amount: bn(a.amount),
+        revocable: a.revocable ?? false,
+        tranches: a.tranches ?? [],
+      }))
+    )
+    .accounts({
+      poolState,
+      vault,
+      releaseHistory,
+      closedState,
+      mint,
+      authorityTokenAccount,
+      treasuryTokenAccount,
+      authority: authority.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY,
+    })
+    .signers([authority])
+    .rpc();
+
+  /// 受益人（或持仓凭证持有人）签名领取
+  const release = (user: Keypair, userTokenAccount = ata(user.publicKey), positionTokenAccount: PublicKey | null = null) =>
+    program.methods
+      .releaseTokens()
+      .accounts({
+        whitelistState: poolState,
+        tokenAccount: vault,
+        userTokenAccount,
+        releaseHistory,
+        positionTokenAccount,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+  /// 代受益人释放到其关联代币账户
+  const crank = (beneficiary: PublicKey) =>
+    program.methods
+      .crankRelease(beneficiary)
+      .accounts({
+        whitelistState: poolState,
+        tokenAccount: vault,
+        beneficiaryTokenAccount: ata(beneficiary),
+        releaseHistory,
+        positionTokenAccount: null,
+        payer: provider.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+  /// 一次为所有受益人释放，按当前受益人地址传入关联代币账户
+  const crankAll = async () => {
+    const state = await program.account.whitelistState.fetch(poolState);
+    return program.methods
+      .crankReleaseAll()
+      .accounts({
+        whitelistState: poolState,
+        tokenAccount: vault,
+        releaseHistory,
+        payer: provider.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        state.beneficiaries.map((b) => ({ pubkey: ata(b.address), isWritable: true, isSigner: false }))
+      )
+      .rpc();
+  };
+
+  /// 管理员指令的账户
+  const asAuthority = { whitelistState: poolState, authority: authority.publicKey };
+
+  return {
+    context,
+    provider,
+    program,
+    authority,
+    beneficiaries,
+    mint,
+    authorityTokenAccount,
+    treasuryTokenAccount,
+    poolId,
+    poolState,
+    vault,
+    releaseHistory,
+    closedState,
+    ata,
+    asAuthority,
+    release,
+    crank,
+    crankAll,
+    fetchState: () => program.account.whitelistState.fetch(poolState),
+    balance: (owner: PublicKey) => tokenBalance(context, ata(owner)),
+  };
+}
+
+export type VestingPool = Awaited<ReturnType<typeof setupPool>>;
@@ -0,0 +1,160 @@
+import * as anchor from "@project-serum/anchor";
+import { BorshAccountsCoder } from "@project-serum/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { AddedAccount } from "solana-bankrun";
+import { assert } from "chai";
+import {
+  PROGRAM_ID,
+  SECONDS_PER_MONTH,
+  fundedAccount,
+  mintAccount,
+  startWhitelist,
+  tokenAccount,
+  tokenBalance,
+  warpTo,
+} from "./helpers";
+
+// 首次部署的白名单状态账户（固定三个白名单字段）升级为当前布局
+
+const START_TIME = 1_700_000_000;
+const AMOUNTS = [BigInt(525_000), BigInt(315_000), BigInt(210_007)];
+const TOTAL_AMOUNT = AMOUNTS.reduce((a, b) => a + b);
+
+/// 按首次部署的 WhitelistState 布局编码账户数据，已领取一个月
+function v1AccountData(authority: PublicKey, whitelists: PublicKey[]): Buffer {
+  const data = Buffer.alloc(8 + 32 * 4 + 8 * 10);
+  BorshAccountsCoder.accountDiscriminator("WhitelistState").copy(data, 0);
+  let offset = 8;
+  for (const key of [authority, ...whitelists]) {
+    key.toBuffer().copy(data, offset);
+    offset += 32;
+  }
+  const claimed = AMOUNTS.map((amount) => amount / BigInt(36));
+  const values = [
+    TOTAL_AMOUNT,
+    ...AMOUNTS,
+    BigInt(START_TIME),
+    BigInt(START_TIME + SECONDS_PER_MONTH),
+    claimed.reduce((a, b) => a + b),
+    ...claimed,
+  ];
+  for (const value of values) {
+    data.writeBigUInt64LE(value, offset);
+    offset += 8;
+  }
+  return data;
+}
+
+describe("migrate_state", () => {
+  const [whitelistState, bump] = PublicKey.findProgramAddressSync([Buffer.from("whitelist_state")], PROGRAM_ID);
+  const [releaseHistory] = PublicKey.findProgramAddressSync(
+    [Buffer.from("release_history"), whitelistState.toBuffer()],
+    PROGRAM_ID
+  );
+
+  const authority = Keypair.generate();
+  const whitelists = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+  const tokenMint = Keypair.generate().publicKey;
+  const whitelistTokenAccount = Keypair.generate().publicKey;
+  const treasuryTokenAccount = Keypair.generate().publicKey;
+  const user1TokenAccount = getAssociatedTokenAddressSync(tokenMint, whitelists[0].publicKey);
+
+  /// 预置版本 1 状态账户、代币账户和有余额的管理员
+  function accounts(stateAuthority: PublicKey): AddedAccount[] {
+    const data = v1AccountData(stateAuthority, whitelists.map((w) => w.publicKey));
+    return [
+      {
+        address: whitelistState,
+        info: { lamports: 10_000_000, data, owner: PROGRAM_ID, executable: false },
+      },
+      fundedAccount(authority.publicKey),
+      fundedAccount(whitelists[0].publicKey),
+      mintAccount(tokenMint, authority.publicKey, TOTAL_AMOUNT),
+      tokenAccount(whitelistTokenAccount, tokenMint, whitelistState, TOTAL_AMOUNT),
+      tokenAccount(treasuryTokenAccount, tokenMint, authority.publicKey, BigInt(0)),
+      tokenAccount(user1TokenAccount, tokenMint, whitelists[0].publicKey, BigInt(0)),
+    ];
+  }
+
+  function migrate(program) {
+    return program.methods
+      .migrateState()
+      .accounts({
+        whitelistState,
+        authority: authority.publicKey,
+        tokenAccount: whitelistTokenAccount,
+        treasuryTokenAccount,
+        releaseHistory,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+  }
+
+  it("原地升级首次部署的状态账户", async () => {
+    const { context, program } = await startWhitelist(accounts(authority.publicKey));
+
+    await migrate(program);
+
+    const state = await program.account.whitelistState.fetch(whitelistState);
+    assert.equal(state.version, 2);
+    assert.ok(state.authority.equals(authority.publicKey));
+    assert.ok(state.treasury.equals(treasuryTokenAccount));
+    assert.equal(state.bump, bump);
+    assert.ok(state.totalAmount.eq(new anchor.BN(TOTAL_AMOUNT.toString())));
+    assert.ok(state.startTime.eq(new anchor.BN(START_TIME)));
+    assert.equal(state.beneficiaries.length, 3);
+    state.beneficiaries.forEach((beneficiary, i) => {
+      assert.ok(beneficiary.address.equals(whitelists[i].publicKey));
+      assert.ok(beneficiary.amount.eq(new anchor.BN(AMOUNTS[i].toString())));
+      assert.ok(beneficiary.claimed.eq(new anchor.BN((AMOUNTS[i] / BigInt(36)).toString())));
+      assert.equal(beneficiary.revocable, false);
+    });
+
+    const history = await program.account.releaseHistory.fetch(releaseHistory);
+    assert.ok(history.whitelistState.equals(whitelistState));
+
+    // 升级后按原释放计划继续领取，不重复领取已领取的月份
+    await warpTo(context, START_TIME + 2 * SECONDS_PER_MONTH);
+    await program.methods
+      .releaseTokens()
+      .accounts({
+        whitelistState,
+        tokenAccount: whitelistTokenAccount,
+        userTokenAccount: user1TokenAccount,
+        releaseHistory,
+        positionTokenAccount: null,
+        user: whitelists[0].publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([whitelists[0]])
+      .rpc();
+    assert.equal(await tokenBalance(context, user1TokenAccount), AMOUNTS[0] / BigInt(36));
+  });
+
+  it("已升级的账户不能重复升级", async () => {
+    const { context, program } = await startWhitelist(accounts(authority.publicKey));
+    await migrate(program);
+
+    // 推进 slot，避免第二次调用被当作重复交易
+    await warpTo(context, START_TIME + SECONDS_PER_MONTH);
+    try {
+      await migrate(program);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("AlreadyMigrated"));
+    }
+  });
+
+  it("只有管理员可以升级", async () => {
+    const { program } = await startWhitelist(accounts(Keypair.generate().publicKey));
+
+    try {
+      await migrate(program);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("NotAuthority"));
+    }
+  });
+});
@@ -0,0 +1,178 @@
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  PROGRAM_ID,
+  SECONDS_PER_DAY,
+  SECONDS_PER_MONTH,
+  TrancheArgs,
+  bn,
+  closedStateAddress,
+  fundedAccount,
+  mintAccount,
+  setupPool,
+  startWhitelist,
+  tokenAccount,
+  tokenBalance,
+  warpTo,
+} from "./helpers";
+
+// 取整策略的性质测试：固定种子随机生成总量、分期和领取时间，全部解锁后领取总和必须等于分配数量
+
+const START_TIME = 1_700_000_000;
+const RELEASE_PERIOD = 36;
+const WHITELIST_ROUNDS = 24;
+const POOL_ROUNDS = 12;
+
+const U64_MASK = (BigInt(1) << BigInt(64)) - BigInt(1);
+
+/// 固定种子的 xorshift 随机数，保证测试可复现
+class Rng {
+  constructor(private state: bigint) {}
+
+  next(): bigint {
+    this.state ^= (this.state << BigInt(13)) & U64_MASK;
+    this.state ^= this.state >> BigInt(7);
+    this.state ^= (this.state << BigInt(17)) & U64_MASK;
+    return this.state;
+  }
+
+  below(bound: number | bigint): bigint {
+    return this.next() % BigInt(bound);
+  }
+
+  int(min: number, max: number): number {
+    return min + Number(this.below(max - min + 1));
+  }
+}
+
+const WHITELIST_ADDRESSES = [
+  new PublicKey("11111111111111111111111111111111"),
+  new PublicKey("22222222222222222222222222222222"),
+  new PublicKey("33333333333333333333333333333333"),
+];
+
+describe("rounding", () => {
+  it("随机总量下白名单三份之和等于总量，36 个月释放之和等于各自分配", async () => {
+    const rng = new Rng(BigInt("0x9e3779b97f4a7c15"));
+    const [whitelistState] = PublicKey.findProgramAddressSync([Buffer.from("whitelist_state")], PROGRAM_ID);
+    const [releaseHistory] = PublicKey.findProgramAddressSync(
+      [Buffer.from("release_history"), whitelistState.toBuffer()],
+      PROGRAM_ID
+    );
+
+    // 除随机总量外覆盖小于释放月数和不能整除的总量
+    const totals = [BigInt(7), BigInt(35), BigInt(21_000_007)];
+    while (totals.length < WHITELIST_ROUNDS) {
+      totals.push(BigInt(1) + rng.below(U64_MASK >> BigInt(1)));
+    }
+
+    for (const total of totals) {
+      const authority = Keypair.generate();
+      const tokenMint = Keypair.generate().publicKey;
+      const whitelistTokenAccount = Keypair.generate().publicKey;
+      const treasuryTokenAccount = Keypair.generate().publicKey;
+      const beneficiaryTokenAccounts = WHITELIST_ADDRESSES.map((address) =>
+        getAssociatedTokenAddressSync(tokenMint, address, true)
+      );
+
+      const { context, program } = await startWhitelist([
+        fundedAccount(authority.publicKey),
+        mintAccount(tokenMint, authority.publicKey, total),
+        tokenAccount(whitelistTokenAccount, tokenMint, whitelistState, total),
+        tokenAccount(treasuryTokenAccount, tokenMint, authority.publicKey, BigInt(0)),
+        ...WHITELIST_ADDRESSES.map((address, i) => tokenAccount(beneficiaryTokenAccounts[i], tokenMint, address, BigInt(0))),
+      ]);
+
+      await warpTo(context, START_TIME);
+      await program.methods
+        .initializeWhitelist([false, false, false])
+        .accounts({
+          whitelistState,
+          authority: authority.publicKey,
+          tokenAccount: whitelistTokenAccount,
+          treasuryTokenAccount,
+          releaseHistory,
+          closedState: closedStateAddress(whitelistState),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+      // 前两份向下取整，余数只归白名单3
+      const state = await program.account.whitelistState.fetch(whitelistState);
+      const amounts = state.beneficiaries.map((b) => BigInt(b.amount.toString()));
+      assert.equal(amounts[0], (total * BigInt(5)) / BigInt(10));
+      assert.equal(amounts[1], (total * BigInt(3)) / BigInt(10));
+      assert.equal(amounts[0] + amounts[1] + amounts[2], total);
+
+      // 每月释放向下取整，取整余数在最后一个月释放
+      const released = [BigInt(0), BigInt(0), BigInt(0)];
+      for (let month = 1; month <= RELEASE_PERIOD; month++) {
+        await warpTo(context, START_TIME + month * SECONDS_PER_MONTH);
+        await program.methods
+          .crankReleaseAll()
+          .accounts({
+            whitelistState,
+            tokenAccount: whitelistTokenAccount,
+            releaseHistory,
+            payer: program.provider.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .remainingAccounts(
+            beneficiaryTokenAccounts.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
+          )
+          .rpc();
+
+        for (let i = 0; i < WHITELIST_ADDRESSES.length; i++) {
+          const balance = await tokenBalance(context, beneficiaryTokenAccounts[i]);
+          const monthly = amounts[i] / BigInt(RELEASE_PERIOD);
+          const expected = month < RELEASE_PERIOD ? monthly : amounts[i] - monthly * BigInt(RELEASE_PERIOD - 1);
+          assert.equal(balance - released[i], expected);
+          released[i] = balance;
+        }
+      }
+
+      assert.deepEqual(released, amounts);
+      assert.equal(await tokenBalance(context, whitelistTokenAccount), BigInt(0));
+    }
+  });
+
+  it("随机分期和不规则时间领取，全部解锁后领取总和等于分配数量", async () => {
+    const rng = new Rng(BigInt("0x2545f4914f6cdd1d"));
+
+    for (let round = 0; round < POOL_ROUNDS; round++) {
+      // 随机组合 TGE 解锁、锁定期和不同间隔的线性释放
+      let finalUnlockTime = START_TIME;
+      const allocations = Array.from({ length: rng.int(1, 10) }, () => {
+        const tranches: TrancheArgs[] = [];
+        let startTime = START_TIME;
+        for (let i = rng.int(1, 4); i > 0; i--) {
+          startTime += rng.int(0, 6 * SECONDS_PER_MONTH);
+          const cliff = rng.int(0, 6) * SECONDS_PER_MONTH;
+          const step = rng.int(1, 90) * SECONDS_PER_DAY;
+          const duration = rng.int(0, 4) == 0 ? 0 : step * rng.int(1, 48) + rng.int(0, step - 1);
+          const amount = BigInt(1) + rng.below(BigInt(1) << BigInt(48));
+          tranches.push({ amount: bn(amount), startTime: bn(startTime), cliff: bn(cliff), duration: bn(duration), step: bn(step) });
+          finalUnlockTime = Math.max(finalUnlockTime, startTime + Math.max(cliff, duration));
+        }
+        const amount = tranches.reduce((sum, t) => sum + BigInt(t.amount.toString()), BigInt(0));
+        return { amount, tranches };
+      });
+
+      const pool = await setupPool({ startTime: START_TIME, allocations });
+
+      let time = START_TIME;
+      while (time < finalUnlockTime) {
+        time = Math.min(time + rng.int(1, 3 * SECONDS_PER_MONTH), finalUnlockTime);
+        await warpTo(pool.context, time);
+        await pool.crankAll();
+      }
+
+      for (let i = 0; i < allocations.length; i++) {
+        assert.equal(await pool.balance(pool.beneficiaries[i].publicKey), allocations[i].amount);
+      }
+      assert.equal(await tokenBalance(pool.context, pool.vault), BigInt(0));
+    }
+  });
+});
@@ -1,9 +1,32 @@
 import * as anchor from "@project-serum/anchor";
 import { Program } from "@project-serum/anchor";
 import { Whitelist } from "../target/types/whitelist";
-import { PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
-import { TOKEN_PROGRAM_ID, createMint, createAccount, mintTo, getAccount } from "@solana/spl-token";
+import { Keypair, PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY, Transaction } from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createAccount,
+  createAssociatedTokenAccountInstruction,
+  createMint,
+  createTransferInstruction,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  mintTo,
+} from "@solana/spl-token";
 import { assert } from "chai";
+import {
+  SECONDS_PER_DAY,
+  SECONDS_PER_MONTH,
+  bn,
+  closedStateAddress,
+  mintAccount,
+  poolAddresses,
+  setupPool,
+  tokenAccount,
+  tokenBalance,
+  VestingPool,
+  warpTo,
+} from "./helpers";
 
 describe("whitelist", () => {
   const provider = anchor.AnchorProvider.env();
@@ -18,6 +41,8 @@ describe("whitelist", () => {
   let user1TokenAccount: PublicKey;
   let user2TokenAccount: PublicKey;
   let user3TokenAccount: PublicKey;
+  let treasuryTokenAccount: PublicKey;
+  let releaseHistory: PublicKey;
   
   // 白名单地址
   const WHITELIST_ADDRESSES = [
@@ -74,6 +99,15 @@ describe("whitelist", () => {
       WHITELIST_ADDRESSES[2]
     );
 
+    // 创建回收账户
+    treasuryTokenAccount = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      tokenMint,
+      provider.wallet.publicKey,
+      anchor.web3.Keypair.generate()
+    );
+
     // 铸造代币到白名单账户
     await mintTo(
       provider.connection,
@@ -91,12 +125,19 @@ describe("whitelist", () => {
       [Buffer.from("whitelist_state")],
       program.programId
     );
+    [releaseHistory] = await PublicKey.findProgramAddress(
+      [Buffer.from("release_history"), whitelistState.toBuffer()],
+      program.programId
+    );
 
-    // 初始化白名单
+    // 初始化白名单，白名单3可撤销
     await program.methods
-      .initializeWhitelist()
+      .initializeWhitelist([false, false, true])
       .accounts({
         whitelistState,
+        treasuryTokenAccount,
+        releaseHistory,
+        closedState: closedStateAddress(whitelistState),
         tokenMint,
         whitelistTokenAccount,
         systemProgram: SystemProgram.programId,
@@ -130,6 +171,21 @@ describe("whitelist", () => {
     assert.ok(claimable1.eq(monthlyAmount1));
   });
 
+  it("查询释放计划", async () => {
+    const schedule = await program.methods
+      .getVestingSchedule(WHITELIST_ADDRESSES[0])
+      .accounts({
+        whitelistState,
+      })
+      .view();
+
+    const state = await program.account.whitelistState.fetch(whitelistState);
+    const SECONDS_PER_MONTH = 30 * 24 * 60 * 60;
+    assert.ok(schedule.vestedAmount.eq(schedule.claimedAmount.add(schedule.claimableAmount)));
+    assert.ok(schedule.finalUnlockTime.eq(state.startTime.add(new anchor.BN(36 * SECONDS_PER_MONTH))));
+    assert.ok(schedule.nextUnlockTime.gt(new anchor.BN(0)));
+  });
+
   it("领取代币", async () => {
     // 用户1领取代币
     await program.methods
@@ -138,6 +194,7 @@ describe("whitelist", () => {
         whitelistState,
         whitelistTokenAccount,
         userTokenAccount: user1TokenAccount,
+        releaseHistory,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([{ publicKey: WHITELIST_ADDRESSES[0], secretKey: Buffer.from([]) }])
@@ -149,6 +206,20 @@ describe("whitelist", () => {
     assert.ok(new anchor.BN(user1Balance.amount.toString()).eq(monthlyAmount1));
   });
 
+  it("领取后写入释放记录", async () => {
+    const history = await program.account.releaseHistory.fetch(releaseHistory);
+    assert.ok(history.whitelistState.equals(whitelistState));
+    assert.ok(history.totalRecords.eq(new anchor.BN(1)));
+
+    const record = history.records[0];
+    assert.ok(record.beneficiary.equals(WHITELIST_ADDRESSES[0]));
+    assert.ok(record.amount.eq(WHITELIST_AMOUNTS[0].div(new anchor.BN(36))));
+    const state = await program.account.whitelistState.fetch(whitelistState);
+    assert.ok(record.fromTime.eq(state.beneficiaries[0].tranches[0].startTime));
+    assert.ok(record.toTime.gt(record.fromTime));
+    assert.ok(record.toTime.eq(history.releasedThrough[0]));
+  });
+
   it("非白名单地址无法查询", async () => {
     const nonWhitelistedAddress = new PublicKey("44444444444444444444444444444444");
     
@@ -181,6 +252,7 @@ describe("whitelist", () => {
           whitelistState,
           whitelistTokenAccount,
           userTokenAccount: nonWhitelistedTokenAccount,
+          releaseHistory,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
         .signers([{ publicKey: nonWhitelistedAddress, secretKey: Buffer.from([]) }])
@@ -190,4 +262,865 @@ describe("whitelist", () => {
       assert.ok(error.toString().includes("AddressNotWhitelisted"));
     }
   });
-}); 
\ No newline at end of file
+
+  it("不可撤销的白名单无法撤销", async () => {
+    try {
+      await program.methods
+        .revokeBeneficiary(WHITELIST_ADDRESSES[0])
+        .accounts({
+          whitelistState,
+          tokenAccount: whitelistTokenAccount,
+          treasuryTokenAccount,
+          authority: provider.wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("NotRevocable"));
+    }
+  });
+
+  const pauseFor = async (address: PublicKey, ms: number) => {
+    await program.methods
+      .pauseBeneficiary(address)
+      .accounts({
+        whitelistState,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+    await new Promise(resolve => setTimeout(resolve, ms));
+    await program.methods
+      .resumeBeneficiary(address)
+      .accounts({
+        whitelistState,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+  };
+
+  it("暂停策略为顺延时，暂停时长计入释放计划", async () => {
+    await program.methods
+      .setPausePolicy(true)
+      .accounts({
+        whitelistState,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const before = await program.account.whitelistState.fetch(whitelistState);
+    await pauseFor(WHITELIST_ADDRESSES[2], 2000);
+    const after = await program.account.whitelistState.fetch(whitelistState);
+
+    assert.ok(after.beneficiaries[2].pausedAt.eq(new anchor.BN(0)));
+    assert.ok(after.beneficiaries[2].pausedDuration.gt(before.beneficiaries[2].pausedDuration));
+    // 其他地址不受影响
+    assert.ok(after.beneficiaries[0].pausedDuration.eq(before.beneficiaries[0].pausedDuration));
+  });
+
+  it("暂停策略为不顺延时，释放时间照常计算", async () => {
+    await program.methods
+      .setPausePolicy(false)
+      .accounts({
+        whitelistState,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const before = await program.account.whitelistState.fetch(whitelistState);
+    await pauseFor(WHITELIST_ADDRESSES[2], 2000);
+    const after = await program.account.whitelistState.fetch(whitelistState);
+
+    assert.ok(after.beneficiaries[2].pausedAt.eq(new anchor.BN(0)));
+    assert.ok(after.beneficiaries[2].pausedDuration.eq(before.beneficiaries[2].pausedDuration));
+  });
+
+  it("暂停中不能修改暂停策略", async () => {
+    await program.methods
+      .pauseBeneficiary(WHITELIST_ADDRESSES[1])
+      .accounts({
+        whitelistState,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .setPausePolicy(true)
+        .accounts({
+          whitelistState,
+          authority: provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("BeneficiaryPaused"));
+    }
+
+    await program.methods
+      .resumeBeneficiary(WHITELIST_ADDRESSES[1])
+      .accounts({
+        whitelistState,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+  });
+
+  it("创建归属池", async () => {
+    const poolId = new anchor.BN(1);
+    const [poolState] = await PublicKey.findProgramAddress(
+      [Buffer.from("vesting_pool"), provider.wallet.publicKey.toBuffer(), poolId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [vault] = await PublicKey.findProgramAddress(
+      [Buffer.from("vesting_vault"), poolState.toBuffer()],
+      program.programId
+    );
+    const [poolReleaseHistory] = await PublicKey.findProgramAddress(
+      [Buffer.from("release_history"), poolState.toBuffer()],
+      program.programId
+    );
+
+    const authorityTokenAccount = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      tokenMint,
+      provider.wallet.publicKey,
+      anchor.web3.Keypair.generate()
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      tokenMint,
+      authorityTokenAccount,
+      provider.wallet.publicKey,
+      3000
+    );
+
+    const team = [anchor.web3.Keypair.generate().publicKey, anchor.web3.Keypair.generate().publicKey];
+    await program.methods
+      .createVestingPool(poolId, "team", new anchor.BN(Math.floor(Date.now() / 1000)), [
+        { address: team[0], amount: new anchor.BN(2000), revocable: true, tranches: [] },
+        { address: team[1], amount: new anchor.BN(1000), revocable: false, tranches: [] },
+      ])
+      .accounts({
+        poolState,
+        vault,
+        releaseHistory: poolReleaseHistory,
+        closedState: closedStateAddress(poolState),
+        mint: tokenMint,
+        authorityTokenAccount,
+        treasuryTokenAccount,
+        authority: provider.wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    const pool = await program.account.whitelistState.fetch(poolState);
+    assert.ok(pool.isPool);
+    assert.equal(pool.name, "team");
+    assert.equal(pool.beneficiaries.length, 2);
+    assert.ok(pool.totalAmount.eq(new anchor.BN(3000)));
+
+    const vaultAccount = await getAccount(provider.connection, vault);
+    assert.equal(vaultAccount.amount.toString(), "3000");
+  });
+});
+// 以下测试使用 solana-bankrun 创建归属池并调整链上时间
+
+const START_TIME = 1_700_000_000;
+
+describe("beneficiary rotation", () => {
+  it("受益人更换地址后由新地址领取，已领取数量保留", async () => {
+    const newOwner = Keypair.generate();
+    const pool = await setupPool({
+      startTime: START_TIME,
+      allocations: [{ amount: BigInt(36_000) }],
+      holders: [newOwner.publicKey],
+    });
+    const [oldOwner] = pool.beneficiaries;
+
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    await pool.release(oldOwner);
+    await pool.program.methods
+      .rotateBeneficiary(newOwner.publicKey)
+      .accounts({ whitelistState: pool.poolState, beneficiary: oldOwner.publicKey })
+      .signers([oldOwner])
+      .rpc();
+
+    const state = await pool.fetchState();
+    assert.ok(state.beneficiaries[0].address.equals(newOwner.publicKey));
+    assert.ok(state.beneficiaries[0].claimed.eqn(1_000));
+
+    await warpTo(pool.context, START_TIME + 2 * SECONDS_PER_MONTH);
+    try {
+      await pool.release(oldOwner);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("AddressNotWhitelisted"));
+    }
+    await pool.release(newOwner);
+    assert.equal(await pool.balance(oldOwner.publicKey), BigInt(1_000));
+    assert.equal(await pool.balance(newOwner.publicKey), BigInt(1_000));
+  });
+
+  it("不能更换为已有的受益人地址", async () => {
+    const pool = await setupPool({
+      startTime: START_TIME,
+      allocations: [{ amount: BigInt(36_000) }, { amount: BigInt(36_000) }],
+    });
+    const [first, second] = pool.beneficiaries;
+
+    try {
+      await pool.program.methods
+        .rotateBeneficiary(second.publicKey)
+        .accounts({ whitelistState: pool.poolState, beneficiary: first.publicKey })
+        .signers([first])
+        .rpc();
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("DuplicateBeneficiary"));
+    }
+  });
+
+  it("管理员恢复地址需等待时间锁结束", async () => {
+    const newOwner = Keypair.generate().publicKey;
+    const pool = await setupPool({ startTime: START_TIME, allocations: [{ amount: BigInt(36_000) }] });
+    const lostOwner = pool.beneficiaries[0].publicKey;
+
+    await warpTo(pool.context, START_TIME);
+    await pool.program.methods
+      .proposeBeneficiaryRecovery(lostOwner, newOwner)
+      .accounts(pool.asAuthority)
+      .signers([pool.authority])
+      .rpc();
+
+    await warpTo(pool.context, START_TIME + 7 * SECONDS_PER_DAY - 1);
+    try {
+      await pool.program.methods
+        .executeBeneficiaryRecovery(lostOwner)
+        .accounts(pool.asAuthority)
+        .signers([pool.authority])
+        .rpc();
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("RecoveryTimelockNotElapsed"));
+    }
+
+    await warpTo(pool.context, START_TIME + 7 * SECONDS_PER_DAY);
+    await pool.program.methods
+      .executeBeneficiaryRecovery(lostOwner)
+      .accounts(pool.asAuthority)
+      .signers([pool.authority])
+      .rpc();
+
+    const state = await pool.fetchState();
+    assert.ok(state.beneficiaries[0].address.equals(newOwner));
+    assert.ok(state.beneficiaries[0].pendingAddress.equals(PublicKey.default));
+  });
+
+  it("时间锁期间受益人可以取消恢复申请", async () => {
+    const pool = await setupPool({ startTime: START_TIME, allocations: [{ amount: BigInt(36_000) }] });
+    const owner = pool.beneficiaries[0];
+
+    await warpTo(pool.context, START_TIME);
+    await pool.program.methods
+      .proposeBeneficiaryRecovery(owner.publicKey, Keypair.generate().publicKey)
+      .accounts(pool.asAuthority)
+      .signers([pool.authority])
+      .rpc();
+    await pool.program.methods
+      .cancelBeneficiaryRecovery(owner.publicKey)
+      .accounts({ whitelistState: pool.poolState, signer: owner.publicKey })
+      .signers([owner])
+      .rpc();
+
+    await warpTo(pool.context, START_TIME + 7 * SECONDS_PER_DAY);
+    try {
+      await pool.program.methods
+        .executeBeneficiaryRecovery(owner.publicKey)
+        .accounts(pool.asAuthority)
+        .signers([pool.authority])
+        .rpc();
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("NoPendingRecovery"));
+    }
+    const state = await pool.fetchState();
+    assert.ok(state.beneficiaries[0].address.equals(owner.publicKey));
+  });
+});
+
+describe("crank_release_all", () => {
+  it("跳过没有可领取代币和暂停中的地址，这些地址可以没有代币账户", async () => {
+    // 受益人0照常释放；受益人1一年后才开始释放；受益人2暂停中；后两者都没有创建代币账户
+    const pool = await setupPool({
+      startTime: START_TIME,
+      allocations: [
+        { amount: BigInt(36_000) },
+        {
+          amount: BigInt(12_000),
+          tranches: [
+            {
+              amount: bn(12_000),
+              startTime: bn(START_TIME + 12 * SECONDS_PER_MONTH),
+              cliff: bn(0),
+              duration: bn(12 * SECONDS_PER_MONTH),
+              step: bn(SECONDS_PER_MONTH),
+            },
+          ],
+        },
+        { amount: BigInt(36_000) },
+      ],
+      withoutAta: [1, 2],
+    });
+    const [b0, b1, b2] = pool.beneficiaries.map((b) => b.publicKey);
+    await pool.program.methods.pauseBeneficiary(b2).accounts(pool.asAuthority).signers([pool.authority]).rpc();
+
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    await pool.crankAll();
+
+    assert.equal(await pool.balance(b0), BigInt(1_000));
+    const state = await pool.fetchState();
+    assert.ok(state.beneficiaries[1].claimed.eqn(0));
+    assert.ok(state.beneficiaries[2].claimed.eqn(0));
+    const history = await pool.program.account.releaseHistory.fetch(pool.releaseHistory);
+    assert.ok(history.totalRecords.eqn(1));
+  });
+
+  it("有可领取代币的地址缺少代币账户时整笔交易失败", async () => {
+    const pool = await setupPool({
+      startTime: START_TIME,
+      allocations: [{ amount: BigInt(36_000) }, { amount: BigInt(36_000) }],
+      withoutAta: [1],
+    });
+
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    try {
+      await pool.crankAll();
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("AccountNotInitialized"));
+    }
+    assert.equal(await pool.balance(pool.beneficiaries[0].publicKey), BigInt(0));
+  });
+});
+
+describe("withdrawal address", () => {
+  const mint = Keypair.generate().publicKey;
+  const otherMint = Keypair.generate().publicKey;
+  const coldWallet = Keypair.generate().publicKey;
+  const coldWalletTokenAccount = Keypair.generate().publicKey;
+  const otherMintTokenAccount = Keypair.generate().publicKey;
+
+  const setup = () =>
+    setupPool({
+      startTime: START_TIME,
+      allocations: [{ amount: BigInt(36_000) }],
+      mint,
+      accounts: [
+        tokenAccount(coldWalletTokenAccount, mint, coldWallet, BigInt(0)),
+        mintAccount(otherMint, coldWallet, BigInt(0)),
+        tokenAccount(otherMintTokenAccount, otherMint, coldWallet, BigInt(0)),
+      ],
+    });
+
+  it("登记的提现地址在延迟结束后才能用于领取", async () => {
+    const pool = await setup();
+    const owner = pool.beneficiaries[0];
+    const registeredAt = START_TIME + SECONDS_PER_MONTH;
+
+    await warpTo(pool.context, registeredAt);
+    await pool.program.methods
+      .setWithdrawalAddress(coldWalletTokenAccount)
+      .accounts({ whitelistState: pool.poolState, beneficiary: owner.publicKey })
+      .signers([owner])
+      .rpc();
+
+    try {
+      await pool.release(owner, coldWalletTokenAccount);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("InvalidDestination"));
+    }
+
+    await warpTo(pool.context, registeredAt + 3 * SECONDS_PER_DAY);
+    await pool.release(owner, coldWalletTokenAccount);
+    const state = await pool.fetchState();
+    assert.equal(await tokenBalance(pool.context, coldWalletTokenAccount), BigInt(state.beneficiaries[0].claimed.toString()));
+    assert.ok(state.beneficiaries[0].claimed.gtn(0));
+  });
+
+  it("接收账户的代币与归属代币不一致时拒绝领取", async () => {
+    const pool = await setup();
+    const owner = pool.beneficiaries[0];
+
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    try {
+      await pool.release(owner, otherMintTokenAccount);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("InvalidTokenAccount"));
+    }
+  });
+});
+
+describe("vesting tranches", () => {
+  // TGE 解锁 1_000，其余 9_000 锁定 3 个月后按月释放，9 个月释放完
+  const tranches = [
+    { amount: bn(1_000), startTime: bn(START_TIME), cliff: bn(0), duration: bn(0), step: bn(SECONDS_PER_MONTH) },
+    {
+      amount: bn(9_000),
+      startTime: bn(START_TIME),
+      cliff: bn(3 * SECONDS_PER_MONTH),
+      duration: bn(9 * SECONDS_PER_MONTH),
+      step: bn(SECONDS_PER_MONTH),
+    },
+  ];
+
+  it("一次领取按所有分期累计计算", async () => {
+    const pool = await setupPool({ startTime: START_TIME, allocations: [{ amount: BigInt(10_000) }] });
+    const owner = pool.beneficiaries[0];
+    await pool.program.methods
+      .setBeneficiaryTranches(owner.publicKey, tranches)
+      .accounts(pool.asAuthority)
+      .signers([pool.authority])
+      .rpc();
+
+    await warpTo(pool.context, START_TIME);
+    await pool.release(owner);
+    assert.equal(await pool.balance(owner.publicKey), BigInt(1_000));
+
+    // 锁定期内没有新的解锁
+    await warpTo(pool.context, START_TIME + 3 * SECONDS_PER_MONTH - 1);
+    try {
+      await pool.release(owner);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("NoMoreReleases"));
+    }
+
+    // 锁定期结束时期间到期的 3 个月一起解锁
+    await warpTo(pool.context, START_TIME + 3 * SECONDS_PER_MONTH);
+    await pool.release(owner);
+    assert.equal(await pool.balance(owner.publicKey), BigInt(4_000));
+
+    await warpTo(pool.context, START_TIME + 9 * SECONDS_PER_MONTH);
+    await pool.release(owner);
+    assert.equal(await pool.balance(owner.publicKey), BigInt(10_000));
+  });
+
+  it("分期总额必须等于分配数量，开始释放后不能修改", async () => {
+    const pool = await setupPool({ startTime: START_TIME, allocations: [{ amount: BigInt(9_999) }] });
+    const owner = pool.beneficiaries[0];
+
+    try {
+      await pool.program.methods
+        .setBeneficiaryTranches(owner.publicKey, tranches)
+        .accounts(pool.asAuthority)
+        .signers([pool.authority])
+        .rpc();
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("TrancheAmountMismatch"));
+    }
+
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    try {
+      await pool.program.methods
+        .setBeneficiaryTranches(owner.publicKey, [{ ...tranches[1], amount: bn(9_999) }])
+        .accounts(pool.asAuthority)
+        .signers([pool.authority])
+        .rpc();
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("VestingAlreadyStarted"));
+    }
+  });
+});
+
+describe("add_allocation", () => {
+  const addAllocation = (pool: VestingPool, signer: Keypair, amount: number, startTime: number) =>
+    pool.program.methods
+      .addAllocation(pool.beneficiaries[0].publicKey, bn(amount), bn(startTime))
+      .accounts({
+        whitelistState: pool.poolState,
+        tokenAccount: pool.vault,
+        authorityTokenAccount: pool.authorityTokenAccount,
+        authority: signer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([signer])
+      .rpc();
+
+  it("追加分配转入代币并新增一个分期", async () => {
+    const pool = await setupPool({
+      startTime: START_TIME,
+      allocations: [{ amount: BigInt(36_000) }],
+      extraTokens: BigInt(3_600),
+    });
+    const owner = pool.beneficiaries[0];
+
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    await addAllocation(pool, pool.authority, 3_600, START_TIME + SECONDS_PER_MONTH);
+
+    const state = await pool.fetchState();
+    assert.ok(state.totalAmount.eqn(39_600));
+    assert.ok(state.beneficiaries[0].amount.eqn(39_600));
+    assert.equal(state.beneficiaries[0].trancheCount, 2);
+    assert.equal(await tokenBalance(pool.context, pool.vault), BigInt(39_600));
+
+    // 原分期释放 2 个月，新分期释放 1 个月
+    await warpTo(pool.context, START_TIME + 2 * SECONDS_PER_MONTH);
+    await pool.release(owner);
+    assert.equal(await pool.balance(owner.publicKey), BigInt(2_000 + 100));
+  });
+
+  it("只有管理员可以追加，新分期不能早于开始释放时间", async () => {
+    const pool = await setupPool({
+      startTime: START_TIME,
+      allocations: [{ amount: BigInt(36_000) }],
+      extraTokens: BigInt(3_600),
+    });
+
+    try {
+      await addAllocation(pool, pool.beneficiaries[0], 3_600, START_TIME);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("NotAuthority"));
+    }
+
+    try {
+      await addAllocation(pool, pool.authority, 3_600, START_TIME - 1);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("InvalidTranche"));
+    }
+    assert.equal(await tokenBalance(pool.context, pool.vault), BigInt(36_000));
+  });
+});
+
+describe("pause policy", () => {
+  const pause = (pool: VestingPool, address: PublicKey) =>
+    pool.program.methods.pauseBeneficiary(address).accounts(pool.asAuthority).signers([pool.authority]).rpc();
+  const resume = (pool: VestingPool, address: PublicKey) =>
+    pool.program.methods.resumeBeneficiary(address).accounts(pool.asAuthority).signers([pool.authority]).rpc();
+  const setPolicy = (pool: VestingPool, extend: boolean) =>
+    pool.program.methods.setPausePolicy(extend).accounts(pool.asAuthority).signers([pool.authority]).rpc();
+
+  it("暂停中不能领取，代领也会被拒绝", async () => {
+    const pool = await setupPool({ startTime: START_TIME, allocations: [{ amount: BigInt(36_000) }] });
+    const owner = pool.beneficiaries[0];
+
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    await pause(pool, owner.publicKey);
+    for (const release of [() => pool.release(owner), () => pool.crank(owner.publicKey)]) {
+      try {
+        await release();
+        assert.fail("应该抛出错误");
+      } catch (error) {
+        assert.ok(error.toString().includes("BeneficiaryPaused"));
+      }
+    }
+    assert.equal(await pool.balance(owner.publicKey), BigInt(0));
+  });
+
+  it("顺延策略下释放计划整体推后暂停时长", async () => {
+    const pool = await setupPool({ startTime: START_TIME, allocations: [{ amount: BigInt(36_000) }] });
+    const owner = pool.beneficiaries[0];
+    await setPolicy(pool, true);
+
+    // 第 1 个月末暂停 2 个月
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    await pause(pool, owner.publicKey);
+    await warpTo(pool.context, START_TIME + 3 * SECONDS_PER_MONTH);
+    await resume(pool, owner.publicKey);
+
+    const state = await pool.fetchState();
+    assert.ok(state.beneficiaries[0].pausedDuration.eqn(2 * SECONDS_PER_MONTH));
+
+    // 恢复时只释放暂停前的 1 个月，第 2 个月推迟到第 4 个月末解锁
+    await pool.release(owner);
+    assert.equal(await pool.balance(owner.publicKey), BigInt(1_000));
+    await warpTo(pool.context, START_TIME + 4 * SECONDS_PER_MONTH - 1);
+    try {
+      await pool.release(owner);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("NoMoreReleases"));
+    }
+    await warpTo(pool.context, START_TIME + 4 * SECONDS_PER_MONTH);
+    await pool.release(owner);
+    assert.equal(await pool.balance(owner.publicKey), BigInt(2_000));
+
+    // 最终解锁时间同样推后 2 个月
+    await warpTo(pool.context, START_TIME + 38 * SECONDS_PER_MONTH - 1);
+    await pool.release(owner);
+    assert.equal(await pool.balance(owner.publicKey), BigInt(35_000));
+    await warpTo(pool.context, START_TIME + 38 * SECONDS_PER_MONTH);
+    await pool.release(owner);
+    assert.equal(await pool.balance(owner.publicKey), BigInt(36_000));
+  });
+
+  it("不顺延策略下恢复后一次领取暂停期间释放的部分", async () => {
+    const pool = await setupPool({ startTime: START_TIME, allocations: [{ amount: BigInt(36_000) }] });
+    const owner = pool.beneficiaries[0];
+
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    await pause(pool, owner.publicKey);
+    await warpTo(pool.context, START_TIME + 4 * SECONDS_PER_MONTH);
+    await resume(pool, owner.publicKey);
+
+    const state = await pool.fetchState();
+    assert.ok(state.beneficiaries[0].pausedDuration.eqn(0));
+    await pool.release(owner);
+    assert.equal(await pool.balance(owner.publicKey), BigInt(4_000));
+  });
+
+  it("有受益人暂停中时不能修改暂停策略", async () => {
+    const pool = await setupPool({
+      startTime: START_TIME,
+      allocations: [{ amount: BigInt(36_000) }, { amount: BigInt(36_000) }],
+    });
+    const paused = pool.beneficiaries[1].publicKey;
+
+    await pause(pool, paused);
+    try {
+      await setPolicy(pool, true);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("BeneficiaryPaused"));
+    }
+
+    await warpTo(pool.context, START_TIME);
+    await resume(pool, paused);
+    await setPolicy(pool, true);
+    const state = await pool.fetchState();
+    assert.ok(state.extendScheduleOnPause);
+  });
+});
+
+describe("position tokens", () => {
+  it("持仓凭证转让后由新持有人领取", async () => {
+    const buyer = Keypair.generate();
+    const pool = await setupPool({
+      startTime: START_TIME,
+      allocations: [{ amount: BigInt(36_000) }],
+      holders: [buyer.publicKey],
+    });
+    const owner = pool.beneficiaries[0];
+    const [positionMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position_mint"), pool.poolState.toBuffer(), owner.publicKey.toBuffer()],
+      pool.program.programId
+    );
+    const ownerPosition = getAssociatedTokenAddressSync(positionMint, owner.publicKey);
+    const buyerPosition = getAssociatedTokenAddressSync(positionMint, buyer.publicKey);
+
+    await pool.program.methods
+      .tokenizePosition()
+      .accounts({
+        whitelistState: pool.poolState,
+        positionMint,
+        positionTokenAccount: ownerPosition,
+        beneficiary: owner.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers([owner])
+      .rpc();
+
+    // 场外转让凭证，无需管理员参与
+    await pool.provider.sendAndConfirm(
+      new Transaction().add(
+        createAssociatedTokenAccountInstruction(owner.publicKey, buyerPosition, buyer.publicKey, positionMint),
+        createTransferInstruction(ownerPosition, buyerPosition, owner.publicKey, 1)
+      ),
+      [owner]
+    );
+
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    await pool.release(buyer, pool.ata(buyer.publicKey), buyerPosition);
+    assert.equal(await pool.balance(buyer.publicKey), BigInt(1_000));
+
+    // 原受益人不再能领取
+    await warpTo(pool.context, START_TIME + 2 * SECONDS_PER_MONTH);
+    try {
+      await pool.release(owner);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("PositionTokenized"));
+    }
+    try {
+      await pool.release(owner, pool.ata(owner.publicKey), ownerPosition);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("NotPositionHolder"));
+    }
+    assert.equal(await pool.balance(owner.publicKey), BigInt(0));
+  });
+});
+
+describe("delegation", () => {
+  const delegate = (pool: VestingPool, owner: Keypair, to: PublicKey) =>
+    pool.program.methods
+      .delegateLockedTokens(to)
+      .accounts({ whitelistState: pool.poolState, positionTokenAccount: null, beneficiary: owner.publicKey })
+      .signers([owner])
+      .rpc();
+  const votingWeight = (pool: VestingPool, owner: PublicKey) =>
+    pool.program.methods.getVotingWeight(owner).accounts({ whitelistState: pool.poolState }).view();
+
+  it("投票权重等于尚未释放的数量，领取后委托自动解除", async () => {
+    const stakingAccount = Keypair.generate().publicKey;
+    const pool = await setupPool({ startTime: START_TIME, allocations: [{ amount: BigInt(36_000) }] });
+    const owner = pool.beneficiaries[0];
+
+    await warpTo(pool.context, START_TIME);
+    await delegate(pool, owner, stakingAccount);
+    let weight = await votingWeight(pool, owner.publicKey);
+    assert.ok(weight.delegate.equals(stakingAccount));
+    assert.ok(weight.weight.eqn(36_000));
+
+    // 已释放未领取的部分不再计入权重
+    await warpTo(pool.context, START_TIME + SECONDS_PER_MONTH);
+    weight = await votingWeight(pool, owner.publicKey);
+    assert.ok(weight.delegate.equals(stakingAccount));
+    assert.ok(weight.weight.eqn(35_000));
+
+    await pool.release(owner);
+    weight = await votingWeight(pool, owner.publicKey);
+    assert.ok(weight.delegate.equals(PublicKey.default));
+    assert.ok(weight.weight.eqn(35_000));
+  });
+
+  it("全部释放后没有可委托的代币", async () => {
+    const pool = await setupPool({ startTime: START_TIME, allocations: [{ amount: BigInt(36_000) }] });
+    const owner = pool.beneficiaries[0];
+
+    await warpTo(pool.context, START_TIME + 36 * SECONDS_PER_MONTH);
+    try {
+      await delegate(pool, owner, Keypair.generate().publicKey);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("NothingToDelegate"));
+    }
+  });
+});
+
+describe("release history", () => {
+  it("写满 64 条后覆盖最早的记录", async () => {
+    // 每分钟解锁 1_000，共 100 次
+    const releases = 70;
+    const pool = await setupPool({
+      startTime: START_TIME,
+      allocations: [
+        {
+          amount: BigInt(100_000),
+          tranches: [{ amount: bn(100_000), startTime: bn(START_TIME), cliff: bn(0), duration: bn(6_000), step: bn(60) }],
+        },
+      ],
+    });
+    const owner = pool.beneficiaries[0].publicKey;
+    for (let i = 1; i <= releases; i++) {
+      await warpTo(pool.context, START_TIME + 60 * i);
+      await pool.crank(owner);
+    }
+
+    const history = await pool.program.account.releaseHistory.fetch(pool.releaseHistory);
+    assert.ok(history.totalRecords.eqn(releases));
+    assert.equal(history.records.length, 64);
+    assert.ok(history.releasedThrough[0].eqn(START_TIME + 60 * releases));
+
+    // 第 k 次释放（从 0 开始）写入 k % 64，前 6 条已被第 64~69 次覆盖
+    history.records.forEach((record, slot) => {
+      const k = slot < releases - 64 ? slot + 64 : slot;
+      assert.ok(record.timestamp.eqn(START_TIME + 60 * (k + 1)));
+      assert.ok(record.amount.eqn(1_000));
+      assert.ok(record.beneficiary.equals(owner));
+    });
+
+    // 最早的记录位于 totalRecords % 64，从这里开始按时间顺序排列
+    const head = releases % 64;
+    const ordered = [...history.records.slice(head), ...history.records.slice(0, head)];
+    assert.ok(ordered[0].timestamp.eqn(START_TIME + 60 * (releases - 63)));
+    for (let i = 1; i < ordered.length; i++) {
+      assert.ok(ordered[i].fromTime.eq(ordered[i - 1].toTime));
+      assert.ok(ordered[i].timestamp.gt(ordered[i - 1].timestamp));
+    }
+  });
+});
+
+describe("finalize", () => {
+  it("结束后的归属池编号不能重新创建", async () => {
+    const pool = await setupPool({
+      startTime: START_TIME,
+      allocations: [
+        {
+          amount: BigInt(1_000),
+          tranches: [{ amount: bn(1_000), startTime: bn(START_TIME), cliff: bn(0), duration: bn(0), step: bn(0) }],
+        },
+      ],
+      extraTokens: BigInt(2_000),
+    });
+    const owner = pool.beneficiaries[0];
+
+    await warpTo(pool.context, START_TIME);
+    await pool.release(owner);
+    await pool.program.methods
+      .finalizeWhitelist()
+      .accounts({
+        whitelistState: pool.poolState,
+        tokenAccount: pool.vault,
+        releaseHistory: pool.releaseHistory,
+        treasuryTokenAccount: pool.treasuryTokenAccount,
+        closedState: pool.closedState,
+        authority: pool.authority.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([pool.authority])
+      .rpc();
+
+    assert.isNull(await pool.context.banksClient.getAccount(pool.poolState));
+    const closed = await pool.program.account.closedState.fetch(pool.closedState);
+    assert.ok(closed.whitelistState.equals(pool.poolState));
+
+    const createPool = (poolId: anchor.BN) => {
+      const addresses = poolAddresses(pool.authority.publicKey, poolId);
+      return pool.program.methods
+        .createVestingPool(poolId, "test", bn(START_TIME), [
+          { address: owner.publicKey, amount: bn(1_000), revocable: false, tranches: [] },
+        ])
+        .accounts({
+          ...addresses,
+          mint: pool.mint,
+          authorityTokenAccount: pool.authorityTokenAccount,
+          treasuryTokenAccount: pool.treasuryTokenAccount,
+          authority: pool.authority.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY,
+        })
+        .signers([pool.authority])
+        .rpc();
+    };
+
+    try {
+      await createPool(pool.poolId);
+      assert.fail("应该抛出错误");
+    } catch (error) {
+      assert.ok(error.toString().includes("StateClosed"));
+    }
+
+    // 新的编号不受影响
+    await createPool(new anchor.BN(2));
+    const created = await pool.program.account.whitelistState.fetch(
+      poolAddresses(pool.authority.publicKey, new anchor.BN(2)).poolState
+    );
+    assert.ok(created.poolId.eqn(2));
+  });
+});